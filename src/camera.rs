@@ -0,0 +1,87 @@
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+use crate::components::{Monster, Position, Renderable, StatusEffects, StatusKind};
+use crate::map::{Map, TileType};
+use crate::{TERM_HEIGHT, TERM_WIDTH};
+
+/// Size of the part of the screen the map is drawn in; the rest belongs to the UI panel.
+pub const VIEW_WIDTH: i32 = TERM_WIDTH;
+pub const VIEW_HEIGHT: i32 = TERM_HEIGHT - 7;
+
+/// Returns the map coordinates of the top-left corner of the viewport, which is centred on the player.
+pub fn get_view_origin(ecs: &World) -> Point {
+    let player_pos = ecs.fetch::<Point>();
+    Point::new(player_pos.x - VIEW_WIDTH / 2, player_pos.y - VIEW_HEIGHT / 2)
+}
+
+/// Translates a screen position (such as the mouse) into map coordinates.
+pub fn screen_to_map(ecs: &World, screen: Point) -> Point {
+    let origin = get_view_origin(ecs);
+    Point::new(screen.x + origin.x, screen.y + origin.y)
+}
+
+/// Translates map coordinates into a screen position. Returns None if the point is outside the viewport.
+pub fn map_to_screen(ecs: &World, pos: Point) -> Option<Point> {
+    let origin = get_view_origin(ecs);
+    let screen = Point::new(pos.x - origin.x, pos.y - origin.y);
+    if screen.x < 0 || screen.x >= VIEW_WIDTH || screen.y < 0 || screen.y >= VIEW_HEIGHT { return None; }
+    Some(screen)
+}
+
+pub fn render_camera(ecs: &World, ctx: &mut BTerm) {
+    let map = ecs.fetch::<Map>();
+    let origin = get_view_origin(ecs);
+
+    // Render the map
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let x = screen_x + origin.x;
+            let y = screen_y + origin.y;
+            if x < 0 || x >= map.width || y < 0 || y >= map.height { continue; }
+
+            let idx = map.xy_idx(x, y);
+            if map.revealed_tiles[idx] {
+                let (glyph, fg, bg) = get_tile_glyph(idx, &map);
+                ctx.set(screen_x, screen_y, fg, bg, glyph);
+            }
+        }
+    }
+
+    // Render entities. A telepathic player senses monsters wherever they are.
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let monsters = ecs.read_storage::<Monster>();
+    let entities = ecs.entities();
+    let telepathic = ecs.read_storage::<StatusEffects>().get(*ecs.fetch::<Entity>())
+        .is_some_and(|statuses| statuses.has(StatusKind::Telepathy));
+    let mut data = (&entities, &positions, &renderables).join().collect::<Vec<_>>();
+    data.sort_by_key(|&(_entity, _pos, render)| std::cmp::Reverse(render.render_order));
+
+    for (entity, pos, render) in data.iter() {
+        if pos.x < 0 || pos.x >= map.width || pos.y < 0 || pos.y >= map.height { continue; }
+        let idx = map.xy_idx(pos.x, pos.y);
+        if map.visible_tiles[idx] || (telepathic && monsters.get(*entity).is_some()) {
+            if let Some(screen) = map_to_screen(ecs, Point::new(pos.x, pos.y)) {
+                ctx.set(screen.x, screen.y, render.fg, render.bg, render.glyph);
+            }
+        }
+    }
+}
+
+fn get_tile_glyph(idx: usize, map: &Map) -> (FontCharType, RGB, RGB) {
+    let glyph;
+    let mut fg;
+    match map.tiles[idx] {
+        TileType::Floor => {
+            glyph = to_cp437('.');
+            fg = RGB::from_f32(0.5, 0.5, 0.5);
+        }
+        TileType::Wall => {
+            glyph = to_cp437('#');
+            fg = RGB::from_f32(0.0, 1.0, 0.0);
+        }
+    }
+    if !map.visible_tiles[idx] { fg = fg.to_greyscale() }
+
+    (glyph, fg, RGB::from_f32(0., 0., 0.))
+}
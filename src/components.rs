@@ -7,6 +7,7 @@ use bracket_lib::color::RGB;
 use specs::saveload::SimpleMarker;
 use serde::{Serialize, Deserialize};
 use crate::State;
+use crate::identification::ItemNames;
 
 #[derive(Component, ConvertSaveload, Copy, Clone)]
 pub struct Position {
@@ -47,8 +48,64 @@ pub struct BlocksTile {}
 pub struct CombatStats {
     pub max_hp: i32,
     pub hp: i32,
-    pub defense: i32,
-    pub power: i32,
+    /// Armour class before attributes and worn armour are taken into account. A to-hit roll has to
+    /// reach the total to land.
+    pub armour_class: i32,
+}
+
+/// A creature's physical attributes, on the usual 3-18 scale where 10 is average.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Attributes {
+    /// Added to melee to-hit and damage rolls.
+    pub might: i32,
+    /// Added to armour class.
+    pub quickness: i32,
+}
+
+impl Attributes {
+    /// The modifier an attribute score gives to rolls: half the difference from 10, rounded down,
+    /// so 8 and 9 give -1, 10 and 11 give 0, and 12 and 13 give +1.
+    pub fn bonus(score: i32) -> i32 {
+        (score - 10).div_euclid(2)
+    }
+
+    /// How many pounds a creature of the given might can carry before being slowed down.
+    pub fn carry_capacity(might: i32) -> f32 {
+        (might * 3) as f32
+    }
+}
+
+/// Dice notation such as 1d6+1: roll `n_dice` dice with `die_type` sides and add `bonus`.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct Dice {
+    pub n_dice: i32,
+    pub die_type: i32,
+    pub bonus: i32,
+}
+
+impl Dice {
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        rng.roll_dice(self.n_dice, self.die_type) + self.bonus
+    }
+}
+
+impl std::fmt::Display for Dice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.bonus {
+            0 => write!(f, "{}d{}", self.n_dice, self.die_type),
+            bonus if bonus > 0 => write!(f, "{}d{}+{}", self.n_dice, self.die_type, bonus),
+            bonus => write!(f, "{}d{}{}", self.n_dice, self.die_type, bonus),
+        }
+    }
+}
+
+/// How a creature fights when it has no weapon in hand, e.g. an orc's fists or a rat's bite.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct NaturalAttack {
+    pub name: String,
+    pub hit_bonus: i32,
+    pub damage: Dice,
+    pub damage_type: DamageType,
 }
 
 #[derive(Component, ConvertSaveload, Debug, Clone)]
@@ -56,22 +113,77 @@ pub struct WantsToMelee {
     pub target: Entity,
 }
 
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Cold,
+    Poison,
+    Magic,
+}
+
+impl DamageType {
+    /// How the damage type reads in messages, e.g. "8 fire damage".
+    pub fn name(&self) -> &'static str {
+        match self {
+            DamageType::Physical => "physical",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Poison => "poison",
+            DamageType::Magic => "magic",
+        }
+    }
+}
+
 #[derive(Component, ConvertSaveload, Debug, Clone)]
 pub struct SufferDamage {
-    pub amount: Vec<i32>,
+    pub amount: Vec<(i32, DamageType)>,
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32, damage_type: DamageType) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, damage_type));
         } else {
-            let dmg = SufferDamage { amount: vec![amount] };
+            let dmg = SufferDamage { amount: vec![(amount, damage_type)] };
             store.insert(victim, dmg).expect("Unable to insert damage");
         }
     }
 }
 
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum DamageResponse {
+    /// Takes half damage.
+    Resistant,
+    /// Takes double damage.
+    Vulnerable,
+    /// Takes no damage at all.
+    Immune,
+}
+
+/// How a creature, or a piece of armour worn by one, responds to particular damage types. Types
+/// that aren't listed do their normal damage.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Resistances {
+    pub responses: Vec<(DamageType, DamageResponse)>,
+}
+
+impl Resistances {
+    /// Works out how much of `amount` gets through, given every response that applies. Immunity
+    /// wins outright; otherwise resistance halves and vulnerability doubles, with one of each
+    /// cancelling out. Several resistances don't stack.
+    pub fn apply(responses: &[DamageResponse], amount: i32) -> i32 {
+        if responses.contains(&DamageResponse::Immune) { return 0; }
+        let resistant = responses.contains(&DamageResponse::Resistant);
+        let vulnerable = responses.contains(&DamageResponse::Vulnerable);
+        match (resistant, vulnerable) {
+            (true, false) => amount / 2,
+            (false, true) => amount * 2,
+            _ => amount,
+        }
+    }
+}
+
 #[derive(Component, Serialize, Deserialize, Debug, Clone)]
 pub struct Item {}
 
@@ -99,12 +211,134 @@ pub struct WantsToUseItem {
 
 #[derive(Component, ConvertSaveload, Debug, Clone)]
 pub struct WantsToDropItem {
-    pub item: Entity
+    pub item: Entity,
+    /// How many of the item's stack to drop.
+    pub quantity: i32,
 }
 
 #[derive(Component, Serialize, Deserialize, Debug, Clone)]
 pub struct Consumable {}
 
+/// How much an item weighs, in pounds.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Weight {
+    pub lbs: f32,
+}
+
+/// Carrying more than they can manage, which slows a creature down.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Encumbered {}
+
+/// What an item is worth to a shopkeeper, in gold pieces.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Value {
+    pub gold: i32,
+}
+
+/// A pile of coins lying on the floor, which goes straight into the wallet when picked up.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Gold {
+    pub amount: i32,
+}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Wallet {
+    pub gold: i32,
+}
+
+/// Buys and sells the contents of its backpack. Bumping into a vendor opens their shop.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Vendor {}
+
+/// Items of the same name with this marker share one line in the inventory, and are picked up and
+/// dropped together. Stacks are matched on name alone, so nothing that can tell two items of the same
+/// name apart, such as charges or a curse, should be stackable.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Stackable {}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Melee,
+    Armour,
+    Ranged,
+}
+
+/// An item that is equipped rather than consumed when used.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+/// Marks an item as being worn or wielded by `owner`. Equipped items are out of the backpack.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct MeleeWeapon {
+    pub hit_bonus: i32,
+    pub damage: Dice,
+    pub damage_type: DamageType,
+}
+
+/// What kind of ammunition a ranged weapon shoots.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AmmoType {
+    Arrow,
+    Bolt,
+}
+
+impl AmmoType {
+    pub fn plural(self) -> &'static str {
+        match self {
+            AmmoType::Arrow => "arrows",
+            AmmoType::Bolt => "bolts",
+        }
+    }
+}
+
+/// A bow or crossbow. Each shot uses up one piece of `ammo` from the shooter's backpack.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct RangedWeapon {
+    pub range: i32,
+    pub hit_bonus: i32,
+    pub damage: Dice,
+    pub ammo: AmmoType,
+}
+
+/// A single arrow or bolt. Ammunition that survives being shot can be picked up again.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Ammunition {
+    pub ammo: AmmoType,
+}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct WantsToShoot {
+    pub target: Point,
+}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Wearable {
+    pub armour_class: i32,
+}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct WantsToRemoveItem {
+    pub item: Entity
+}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct WantsToThrowItem {
+    pub item: Entity,
+    pub target: Point,
+}
+
+/// Breaks when thrown, splashing its contents over the tiles around where it lands.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Shatters {}
+
 #[derive(Component, ConvertSaveload, Debug, Clone)]
 pub struct Ranged {
     pub range: i32,
@@ -113,6 +347,7 @@ pub struct Ranged {
 #[derive(Component, ConvertSaveload, Debug, Clone)]
 pub struct InflictsDamage {
     pub damage: i32,
+    pub damage_type: DamageType,
 }
 
 #[derive(Component, ConvertSaveload, Debug, Clone)]
@@ -120,16 +355,246 @@ pub struct AreaOfEffect {
     pub radius: i32,
 }
 
-#[derive(Component, ConvertSaveload, Debug, Clone)]
-pub struct Confusion {
+/// Kinds of temporary condition that can affect any creature, including the player.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum StatusKind {
+    Confusion,
+    Poison,
+    Regeneration,
+    Slow,
+    Haste,
+    Paralysis,
+    /// Can sense monsters anywhere on the level.
+    Telepathy,
+}
+
+/// How applying a status to a creature that already has it combines with the existing one.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Stacking {
+    /// The longer of the two durations wins.
+    Refresh,
+    /// The durations are added together.
+    Extend,
+}
+
+impl StatusKind {
+    pub fn stacking(self) -> Stacking {
+        match self {
+            StatusKind::Poison | StatusKind::Regeneration => Stacking::Extend,
+            _ => Stacking::Refresh,
+        }
+    }
+
+    /// A status that this one cancels out when applied, rather than coexisting with it.
+    pub fn opposite(self) -> Option<StatusKind> {
+        match self {
+            StatusKind::Slow => Some(StatusKind::Haste),
+            StatusKind::Haste => Some(StatusKind::Slow),
+            _ => None,
+        }
+    }
+
+    /// Whether a creature with this status loses its turns.
+    pub fn prevents_action(self) -> bool {
+        matches!(self, StatusKind::Paralysis)
+    }
+
+    /// Describes a creature with this status, e.g. "confused".
+    pub fn adjective(self) -> &'static str {
+        match self {
+            StatusKind::Confusion => "confused",
+            StatusKind::Poison => "poisoned",
+            StatusKind::Regeneration => "regenerating",
+            StatusKind::Slow => "slowed",
+            StatusKind::Haste => "hasted",
+            StatusKind::Paralysis => "paralyzed",
+            StatusKind::Telepathy => "telepathic",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
     pub turns: i32,
 }
 
+/// The status effects currently affecting a creature. Ticked down by `StatusEffectSystem`.
+#[derive(Component, ConvertSaveload, Default, Debug, Clone)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn add_effect(store: &mut WriteStorage<StatusEffects>, victim: Entity, effect: StatusEffect) {
+        if store.get(victim).is_none() {
+            store.insert(victim, StatusEffects::default()).expect("Unable to insert status effects");
+        }
+        let statuses = store.get_mut(victim).unwrap();
+
+        if let Some(opposite) = effect.kind.opposite() {
+            if statuses.has(opposite) {
+                statuses.effects.retain(|e| e.kind != opposite);
+                return;
+            }
+        }
+
+        match statuses.effects.iter_mut().find(|e| e.kind == effect.kind) {
+            None => statuses.effects.push(effect),
+            Some(existing) => match effect.kind.stacking() {
+                Stacking::Refresh => existing.turns = i32::max(existing.turns, effect.turns),
+                Stacking::Extend => existing.turns += effect.turns,
+            }
+        }
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    /// Returns true if the creature can act this turn.
+    pub fn can_act(&self) -> bool {
+        !self.effects.iter().any(|e| e.kind.prevents_action())
+    }
+}
+
+/// An item that applies a status effect to whatever it is used on.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct InflictsStatus {
+    pub effect: StatusEffect,
+}
+
+/// A monster's memory of where it last saw the player, which it will head for and search around
+/// for a while after losing sight of them.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Pursuit {
+    pub last_seen: Point,
+    pub search_turns: i32,
+}
+
+/// What a monster does while it has no quarry to chase.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum IdleBehaviour {
+    /// Ambles about at random.
+    Wander,
+    /// Walks a circuit of waypoints, heading for `route[next]`.
+    Patrol { route: Vec<Point>, next: usize },
+    /// Does nothing until woken by noise or by the player coming close, then wanders.
+    Sleep,
+}
+
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Idle {
+    pub behaviour: IdleBehaviour,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum HungerState {
+    WellFed,
+    Normal,
+    Hungry,
+    Starving,
+}
+
+/// Counts down how long until a creature gets hungrier. `duration` is measured in the creature's
+/// own turns.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct HungerClock {
+    pub state: HungerState,
+    pub duration: i32,
+}
+
+/// An item that can be used several times before running out, such as a wand.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Charges {
+    pub current: i32,
+    pub max: i32,
+}
+
+/// Reading this tops up the charges of everything the reader is carrying.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Recharges {}
+
+/// Reveals the whole level to the player who uses it.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct MagicMapper {}
+
+/// Whisks its user away to a random spot on the level.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct TeleportsUser {}
+
+/// Moves its user to the targeted tile.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Blinks {}
+
+/// Turns the walls on the line from its user to the target into floor.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Digs {}
+
+/// Equipment that can't be taken off, dropped, thrown or sold once it has been put on. Until then it
+/// is no harder to get rid of than anything else.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Cursed {}
+
+/// Cursed items carry this on the end of their real name, until the curse is lifted.
+pub const CURSED_SUFFIX: &str = " (cursed)";
+
+/// Reading this lifts the curse from everything the reader is carrying or wearing.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct RemovesCurse {}
+
+/// Reading this identifies every item the reader is carrying.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Identifies {}
+
+/// Food, which resets the eater's hunger clock to well fed.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct ProvidesFood {}
+
+/// The remains of a dead creature.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+pub struct Corpse {}
+
+/// One possible drop from a `LootTable`: the name of an item the spawner knows how to make, and
+/// the percentage chance of it dropping.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LootDrop {
+    pub item: String,
+    pub chance: i32,
+}
+
+/// Items a creature may leave behind when it dies, on top of whatever it was carrying. Each drop
+/// is rolled for separately.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct LootTable {
+    pub drops: Vec<LootDrop>,
+}
+
+/// Which side a creature is on. How factions feel about each other is looked up with
+/// `faction::reaction`.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Faction {
+    pub name: String,
+}
+
+/// How quickly a creature gets turns. Each tick it gains `speed` energy, and it acts whenever it has
+/// saved up enough.
+#[derive(Component, ConvertSaveload, Debug, Clone)]
+pub struct Energy {
+    pub speed: i32,
+    pub current: i32,
+}
+
+/// Marks the creatures that get to act during the current tick.
+#[derive(Component, Debug, Clone)]
+pub struct MyTurn {}
+
 pub struct SerializeMe;
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
+    pub item_names: ItemNames,
 }
 
 pub fn register_all_components(gs: &mut State) {
@@ -150,10 +615,111 @@ pub fn register_all_components(gs: &mut State) {
     gs.ecs.register::<WantsToUseItem>();
     gs.ecs.register::<WantsToDropItem>();
     gs.ecs.register::<Consumable>();
+    gs.ecs.register::<Stackable>();
+    gs.ecs.register::<Value>();
+    gs.ecs.register::<Gold>();
+    gs.ecs.register::<Wallet>();
+    gs.ecs.register::<Vendor>();
+    gs.ecs.register::<Weight>();
+    gs.ecs.register::<Encumbered>();
     gs.ecs.register::<Ranged>();
     gs.ecs.register::<InflictsDamage>();
     gs.ecs.register::<AreaOfEffect>();
-    gs.ecs.register::<Confusion>();
+    gs.ecs.register::<StatusEffects>();
+    gs.ecs.register::<InflictsStatus>();
+    gs.ecs.register::<Energy>();
+    gs.ecs.register::<MyTurn>();
+    gs.ecs.register::<Pursuit>();
+    gs.ecs.register::<Idle>();
+    gs.ecs.register::<Faction>();
+    gs.ecs.register::<Corpse>();
+    gs.ecs.register::<LootTable>();
+    gs.ecs.register::<Attributes>();
+    gs.ecs.register::<NaturalAttack>();
+    gs.ecs.register::<Equippable>();
+    gs.ecs.register::<Equipped>();
+    gs.ecs.register::<MeleeWeapon>();
+    gs.ecs.register::<Wearable>();
+    gs.ecs.register::<WantsToRemoveItem>();
+    gs.ecs.register::<WantsToThrowItem>();
+    gs.ecs.register::<RangedWeapon>();
+    gs.ecs.register::<Ammunition>();
+    gs.ecs.register::<WantsToShoot>();
+    gs.ecs.register::<Shatters>();
+    gs.ecs.register::<Resistances>();
+    gs.ecs.register::<HungerClock>();
+    gs.ecs.register::<ProvidesFood>();
+    gs.ecs.register::<Identifies>();
+    gs.ecs.register::<Cursed>();
+    gs.ecs.register::<RemovesCurse>();
+    gs.ecs.register::<Charges>();
+    gs.ecs.register::<Recharges>();
+    gs.ecs.register::<MagicMapper>();
+    gs.ecs.register::<TeleportsUser>();
+    gs.ecs.register::<Blinks>();
+    gs.ecs.register::<Digs>();
     gs.ecs.register::<SimpleMarker<SerializeMe>>();
     gs.ecs.register::<SerializationHelper>();
 }
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+    use super::{Attributes, StatusEffect, StatusEffects, StatusKind};
+
+    fn affected(effects: &[(StatusKind, i32)]) -> Option<StatusEffects> {
+        let mut world = World::new();
+        world.register::<StatusEffects>();
+        let victim = world.create_entity().build();
+        let mut store = world.write_storage::<StatusEffects>();
+        for (kind, turns) in effects {
+            StatusEffects::add_effect(&mut store, victim, StatusEffect { kind: *kind, turns: *turns });
+        }
+        store.get(victim).cloned()
+    }
+
+    fn turns(statuses: &StatusEffects, kind: StatusKind) -> Option<i32> {
+        statuses.effects.iter().find(|e| e.kind == kind).map(|e| e.turns)
+    }
+
+    #[test]
+    fn attribute_bonuses_round_down() {
+        assert_eq!(Attributes::bonus(8), -1);
+        assert_eq!(Attributes::bonus(9), -1);
+        assert_eq!(Attributes::bonus(10), 0);
+        assert_eq!(Attributes::bonus(11), 0);
+    }
+
+    #[test]
+    fn extending_statuses_add_their_durations() {
+        let statuses = affected(&[(StatusKind::Poison, 3), (StatusKind::Poison, 4)]).unwrap();
+        assert_eq!(turns(&statuses, StatusKind::Poison), Some(7));
+        assert_eq!(statuses.effects.len(), 1);
+    }
+
+    #[test]
+    fn refreshing_statuses_keep_the_longer_duration() {
+        let statuses = affected(&[(StatusKind::Confusion, 4), (StatusKind::Confusion, 2)]).unwrap();
+        assert_eq!(turns(&statuses, StatusKind::Confusion), Some(4));
+        let statuses = affected(&[(StatusKind::Confusion, 2), (StatusKind::Confusion, 5)]).unwrap();
+        assert_eq!(turns(&statuses, StatusKind::Confusion), Some(5));
+    }
+
+    #[test]
+    fn opposite_statuses_cancel_out() {
+        let statuses = affected(&[(StatusKind::Slow, 6), (StatusKind::Haste, 8)]).unwrap();
+        assert!(!statuses.has(StatusKind::Slow));
+        assert!(!statuses.has(StatusKind::Haste));
+
+        let statuses = affected(&[(StatusKind::Haste, 8), (StatusKind::Slow, 6), (StatusKind::Slow, 6)]).unwrap();
+        assert_eq!(turns(&statuses, StatusKind::Slow), Some(6));
+        assert!(!statuses.has(StatusKind::Haste));
+    }
+
+    #[test]
+    fn only_paralysis_stops_a_creature_acting() {
+        assert!(StatusEffects::default().can_act());
+        assert!(affected(&[(StatusKind::Slow, 6), (StatusKind::Confusion, 4)]).unwrap().can_act());
+        assert!(!affected(&[(StatusKind::Paralysis, 3)]).unwrap().can_act());
+    }
+}
@@ -1,19 +1,71 @@
-use bracket_lib::prelude::console;
+use std::cmp::Ordering;
+use bracket_lib::prelude::{console, RandomNumberGenerator};
 use specs::prelude::*;
-use crate::components::{CombatStats, Name, Player, SufferDamage};
+use crate::components::{CombatStats, DamageResponse, Equipped, InBackpack, LootTable, Name, Player, Position, Resistances, SufferDamage, Wallet};
 use crate::gamelog::GameLog;
+use crate::map::Map;
+use crate::player::AutoPilot;
+use crate::spawner;
 
+/// Applies pending damage after resistances, and reports what actually got through for the player
+/// and anything they can see.
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
-    type SystemData = (WriteStorage<'a, CombatStats>,
-                       WriteStorage<'a, SufferDamage>);
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       ReadExpect<'a, Map>,
+                       WriteExpect<'a, AutoPilot>,
+                       WriteStorage<'a, CombatStats>,
+                       WriteStorage<'a, SufferDamage>,
+                       ReadStorage<'a, Resistances>,
+                       ReadStorage<'a, Equipped>,
+                       ReadStorage<'a, Name>,
+                       ReadStorage<'a, Position>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (entities, player_entity, mut log, map, mut autopilot, mut stats, mut damage, resistances, equipped, names, positions) = data;
 
-        for (stats, damage) in (&mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            if entity == *player_entity {
+                autopilot.hit = true;
+            }
+
+            // Resistances can come from the creature itself or from anything it is wearing
+            let mut sources: Vec<&Resistances> = (&equipped, &resistances).join()
+                .filter(|(equipped, _resistances)| equipped.owner == entity)
+                .map(|(_equipped, resistances)| resistances)
+                .collect();
+            if let Some(own) = resistances.get(entity) {
+                sources.push(own);
+            }
+
+            for (amount, damage_type) in damage.amount.iter() {
+                let responses: Vec<DamageResponse> = sources.iter()
+                    .flat_map(|resistances| resistances.responses.iter())
+                    .filter(|(resisted, _response)| resisted == damage_type)
+                    .map(|(_resisted, response)| *response)
+                    .collect();
+                let dealt = Resistances::apply(&responses, *amount);
+                stats.hp -= dealt;
+
+                let seen = entity == *player_entity
+                    || positions.get(entity).is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)]);
+                if !seen { continue; }
+                let Some(victim_name) = names.get(entity) else { continue };
+                if responses.contains(&DamageResponse::Immune) {
+                    log.entries.push(format!("{} is immune to {} damage.", &victim_name.name, damage_type.name()));
+                } else {
+                    let outcome = match dealt.cmp(amount) {
+                        Ordering::Less => " (resisted)",
+                        Ordering::Greater => " (vulnerable)",
+                        Ordering::Equal => "",
+                    };
+                    log.entries.push(format!("{} takes {} {} damage{}.", &victim_name.name, dealt, damage_type.name(), outcome));
+                }
+            }
         }
 
         damage.clear();
@@ -47,6 +99,60 @@ pub fn delete_the_dead(ecs: &mut World) {
     }
 
     for victim in dead {
+        leave_remains(ecs, victim);
         ecs.delete_entity(victim).expect("Unable to delete");
     }
 }
+
+/// Leaves a dead creature's corpse where it fell, along with everything it was carrying, the gold
+/// in its wallet and whatever its loot table turns up.
+fn leave_remains(ecs: &mut World, victim: Entity) {
+    let pos = match ecs.read_storage::<Position>().get(victim) {
+        Some(pos) => *pos,
+        None => return,
+    };
+
+    // Empty their pockets and strip off their gear
+    {
+        let entities = ecs.entities();
+        let mut backpack = ecs.write_storage::<InBackpack>();
+        let mut equipped = ecs.write_storage::<Equipped>();
+        let mut positions = ecs.write_storage::<Position>();
+        let carried: Vec<Entity> = (&entities, &backpack).join()
+            .filter(|(_item, pack)| pack.owner == victim)
+            .map(|(item, _pack)| item)
+            .collect();
+        let worn: Vec<Entity> = (&entities, &equipped).join()
+            .filter(|(_item, equipped)| equipped.owner == victim)
+            .map(|(item, _equipped)| item)
+            .collect();
+        for item in carried.into_iter().chain(worn) {
+            backpack.remove(item);
+            equipped.remove(item);
+            positions.insert(item, pos).expect("Unable to insert position");
+        }
+    }
+
+    let gold = ecs.read_storage::<Wallet>().get(victim).map_or(0, |wallet| wallet.gold);
+    if gold > 0 {
+        spawner::gold_pile(ecs, pos.x, pos.y, gold);
+    }
+
+    let mut loot = Vec::new();
+    if let Some(table) = ecs.read_storage::<LootTable>().get(victim) {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        for drop in table.drops.iter() {
+            if rng.roll_dice(1, 100) <= drop.chance {
+                loot.push(drop.item.clone());
+            }
+        }
+    }
+    for item in loot {
+        spawner::named_item(ecs, &item, pos.x, pos.y);
+    }
+
+    let name = ecs.read_storage::<Name>().get(victim).map(|name| name.name.clone());
+    if let Some(name) = name {
+        spawner::corpse(ecs, pos.x, pos.y, &name);
+    }
+}
@@ -0,0 +1,67 @@
+use std::ops::Deref;
+use specs::prelude::*;
+use specs::storage::MaskedStorage;
+use crate::components::{Attributes, Encumbered, Equipped, InBackpack, Weight};
+use crate::gamelog::GameLog;
+
+/// How far past their capacity a creature can load themselves before they can't pick anything
+/// else up.
+pub const MAX_LOAD_MULTIPLIER: f32 = 2.0;
+
+/// Weighs what each creature is carrying and wearing, and marks those carrying more than their
+/// might allows as encumbered.
+pub struct EncumbranceSystem {}
+
+impl<'a> System<'a> for EncumbranceSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       ReadStorage<'a, Attributes>,
+                       ReadStorage<'a, InBackpack>,
+                       ReadStorage<'a, Equipped>,
+                       ReadStorage<'a, Weight>,
+                       WriteStorage<'a, Encumbered>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player_entity, mut gamelog, attributes, backpack, equipped, weights, mut encumbered) = data;
+
+        for (entity, attributes) in (&entities, &attributes).join() {
+            let load = weigh_load(&backpack, &equipped, &weights, entity);
+            let overloaded = load > Attributes::carry_capacity(attributes.might);
+            let was_overloaded = encumbered.get(entity).is_some();
+
+            if overloaded && !was_overloaded {
+                encumbered.insert(entity, Encumbered {}).expect("Unable to insert encumbrance");
+                if entity == *player_entity {
+                    gamelog.entries.push("You are overburdened, and slow down.".to_string());
+                }
+            } else if !overloaded && was_overloaded {
+                encumbered.remove(entity);
+                if entity == *player_entity {
+                    gamelog.entries.push("You are no longer overburdened.".to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Total weight of everything `owner` is carrying or wearing.
+pub fn carried_weight(ecs: &World, owner: Entity) -> f32 {
+    weigh_load(&ecs.read_storage::<InBackpack>(), &ecs.read_storage::<Equipped>(), &ecs.read_storage::<Weight>(), owner)
+}
+
+/// `carried_weight` for systems, which already hold the storages involved.
+pub fn weigh_load<D>(backpack: &Storage<'_, InBackpack, D>, equipped: &ReadStorage<'_, Equipped>, weights: &ReadStorage<'_, Weight>, owner: Entity) -> f32
+    where D: Deref<Target = MaskedStorage<InBackpack>>
+{
+    let in_pack: f32 = (backpack, weights).join()
+        .filter(|(pack, _weight)| pack.owner == owner)
+        .map(|(_pack, weight)| weight.lbs)
+        .sum();
+    let worn: f32 = (equipped, weights).join()
+        .filter(|(equipped, _weight)| equipped.owner == owner)
+        .map(|(_equipped, weight)| weight.lbs)
+        .sum();
+    in_pack + worn
+}
@@ -0,0 +1,30 @@
+/// How a creature of one faction behaves towards a creature of another.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reaction {
+    Attack,
+    Ignore,
+    Flee,
+}
+
+pub const PLAYER: &str = "Player";
+pub const ORCS: &str = "Orcs";
+pub const GOBLINS: &str = "Goblins";
+pub const VERMIN: &str = "Vermin";
+pub const SHOPKEEPERS: &str = "Shopkeepers";
+
+/// Who feels what about whom. Reactions are one-way: the first faction's attitude to the second.
+const REACTIONS: &[(&str, &str, Reaction)] = &[
+    (ORCS, PLAYER, Reaction::Attack),
+    (ORCS, VERMIN, Reaction::Attack),
+    (GOBLINS, PLAYER, Reaction::Attack),
+    (VERMIN, PLAYER, Reaction::Flee),
+    (VERMIN, ORCS, Reaction::Flee),
+];
+
+/// Looks up how `mine` reacts to `theirs`. Creatures leave their own faction, and anyone not listed
+/// in the table, alone.
+pub fn reaction(mine: &str, theirs: &str) -> Reaction {
+    REACTIONS.iter()
+        .find(|(from, to, _)| *from == mine && *to == theirs)
+        .map_or(Reaction::Ignore, |(_, _, reaction)| *reaction)
+}
@@ -1,10 +1,15 @@
+use std::collections::HashSet;
 use bracket_lib::prelude::*;
 use specs::prelude::*;
 
+use crate::camera::{map_to_screen, screen_to_map, VIEW_HEIGHT, VIEW_WIDTH};
 use crate::components::*;
 use crate::gamelog::GameLog;
-use crate::map::Map;
-use crate::{RunState, State, TERM_HEIGHT};
+use crate::encumbrance_system::carried_weight;
+use crate::identification::ItemNames;
+use crate::inventory_system::carried_stack;
+use crate::map::{Map, TileType};
+use crate::{shop, RunState, ShopMode, State, TERM_HEIGHT};
 use crate::gui::MainMenuSelection::{LoadGame, NewGame, Quit};
 
 pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
@@ -19,6 +24,42 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
         ctx.draw_bar_horizontal(28, TERM_HEIGHT - 7, 51, stats.hp, stats.max_hp, RGB::named(RED), RGB::named(BLACK));
     }
 
+    let wallets = ecs.read_storage::<Wallet>();
+    if let Some(wallet) = wallets.get(*ecs.fetch::<Entity>()) {
+        ctx.print_color(2, TERM_HEIGHT - 7, RGB::named(GOLD), RGB::named(BLACK), format!(" {}gp ", wallet.gold));
+    }
+
+    // Player status effects, along the bottom edge of the panel
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    if let Some(statuses) = status_effects.get(*ecs.fetch::<Entity>()) {
+        let mut x = 2;
+        for effect in statuses.effects.iter() {
+            let label = format!(" {} ({}) ", effect.kind.adjective(), effect.turns);
+            ctx.print_color(x, TERM_HEIGHT - 1, status_color(effect.kind), RGB::named(BLACK), &label);
+            x += label.len() as i32;
+        }
+    }
+
+    // Hunger and encumbrance, in the bottom right corner
+    let mut x = 79;
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+    if let Some(clock) = hunger_clocks.get(*ecs.fetch::<Entity>()) {
+        let hunger = match clock.state {
+            HungerState::WellFed => Some((" Well Fed ", RGB::named(GREEN))),
+            HungerState::Normal => None,
+            HungerState::Hungry => Some((" Hungry ", RGB::named(ORANGE))),
+            HungerState::Starving => Some((" Starving ", RGB::named(RED))),
+        };
+        if let Some((label, color)) = hunger {
+            x -= label.len() as i32;
+            ctx.print_color(x, TERM_HEIGHT - 1, color, RGB::named(BLACK), label);
+        }
+    }
+    if ecs.read_storage::<Encumbered>().get(*ecs.fetch::<Entity>()).is_some() {
+        let label = " Overburdened ";
+        ctx.print_color(x - label.len() as i32, TERM_HEIGHT - 1, RGB::named(ORANGE), RGB::named(BLACK), label);
+    }
+
     let log = ecs.fetch::<GameLog>();
 
     // let mut y = 44;
@@ -43,15 +84,32 @@ pub fn draw_ui(ecs: &World, ctx: &mut BTerm) {
 fn draw_tooltips(ecs: &World, ctx: &mut BTerm) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
+    let item_names = ecs.fetch::<ItemNames>();
     let positions = ecs.read_storage::<Position>();
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    let idles = ecs.read_storage::<Idle>();
+    let entities = ecs.entities();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height { return; }
+    if mouse_pos.0 >= VIEW_WIDTH || mouse_pos.1 >= VIEW_HEIGHT { return; }
+    let mouse_map_pos = screen_to_map(ecs, Point::new(mouse_pos.0, mouse_pos.1));
+    if mouse_map_pos.x < 0 || mouse_map_pos.x >= map.width || mouse_map_pos.y < 0 || mouse_map_pos.y >= map.height { return; }
     let mut tooltip: Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
+    for (entity, name, position) in (&entities, &names, &positions).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
-            tooltip.push(name.name.to_string());
+        if position.x == mouse_map_pos.x && position.y == mouse_map_pos.y && map.visible_tiles[idx] {
+            let mut tip = item_names.describe(&name.name);
+            let mut adjectives: Vec<&str> = Vec::new();
+            if idles.get(entity).is_some_and(|idle| idle.behaviour == IdleBehaviour::Sleep) {
+                adjectives.push("asleep");
+            }
+            if let Some(statuses) = status_effects.get(entity) {
+                adjectives.extend(statuses.effects.iter().map(|e| e.kind.adjective()));
+            }
+            if !adjectives.is_empty() {
+                tip = format!("{} ({})", tip, adjectives.join(", "));
+            }
+            tooltip.push(tip);
         }
     }
 
@@ -92,6 +150,18 @@ fn draw_tooltips(ecs: &World, ctx: &mut BTerm) {
     }
 }
 
+fn status_color(kind: StatusKind) -> RGB {
+    match kind {
+        StatusKind::Confusion => RGB::named(PINK),
+        StatusKind::Poison => RGB::named(GREEN),
+        StatusKind::Regeneration => RGB::named(CYAN),
+        StatusKind::Slow => RGB::named(BLUE),
+        StatusKind::Haste => RGB::named(ORANGE),
+        StatusKind::Paralysis => RGB::named(MAGENTA),
+        StatusKind::Telepathy => RGB::named(YELLOW),
+    }
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum ItemMenuResult {
     Cancel,
@@ -107,30 +177,67 @@ pub enum TargetingResult {
 }
 
 pub fn item_menu(gs: &mut State, ctx: &mut BTerm, title: &str) -> ItemMenuResult {
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let entities = gs.ecs.entities();
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let items = pack_contents(&gs.ecs, player_entity);
+
+    let load = {
+        let capacity = gs.ecs.read_storage::<Attributes>().get(player_entity).map_or(0.0, |attr| Attributes::carry_capacity(attr.might));
+        format!("Carrying {:.1} / {:.1} lbs", carried_weight(&gs.ecs, player_entity), capacity)
+    };
+    item_list_menu(ctx, title, &items, Some(&load))
+}
 
-    let inventory = (&backpack, &names).join().filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+/// Describes what is in `owner`'s backpack, one line per item, with stacks listed once under their
+/// first item.
+fn pack_contents(ecs: &World, owner: Entity) -> Vec<(Entity, String)> {
+    let names = ecs.read_storage::<Name>();
+    let item_names = ecs.fetch::<ItemNames>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let charges = ecs.read_storage::<Charges>();
+    let stackable = ecs.read_storage::<Stackable>();
+    let entities = ecs.entities();
+
+    let mut items: Vec<(Entity, String)> = Vec::new();
+    let mut stacks: HashSet<String> = HashSet::new();
+    for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == owner) {
+        let mut count = 1;
+        if stackable.get(entity).is_some() {
+            if !stacks.insert(name.name.clone()) { continue; }
+            count = carried_stack(&entities, &backpack, &names, &stackable, owner, &name.name).len();
+        }
+
+        let mut description = item_names.describe(&name.name);
+        if let Some(charges) = charges.get(entity) {
+            description = format!("{} ({}/{})", description, charges.current, charges.max);
+        }
+        if count > 1 {
+            description = format!("{} (x{})", description, count);
+        }
+        items.push((entity, description));
+    }
+    items
+}
+
+/// Lists `items` in a lettered menu and lets the player pick one, with an optional line of
+/// `footer` text underneath.
+fn item_list_menu(ctx: &mut BTerm, title: &str, items: &[(Entity, String)], footer: Option<&str>) -> ItemMenuResult {
+    let count = items.len();
 
     let mut y = (25 - (count / 2)) as i32;
     ctx.draw_box(15, y - 2, 31, (count + 3) as i32, RGB::named(WHITE), RGB::named(BLACK));
     ctx.print_color(18, y - 2, RGB::named(YELLOW), RGB::named(BLACK), title);
     ctx.print_color(18, y + count as i32 + 1, RGB::named(YELLOW), RGB::named(BLACK), "ESCAPE to cancel");
+    if let Some(footer) = footer {
+        ctx.print_color(17, y + count as i32, RGB::named(GREY), RGB::named(BLACK), footer);
+    }
 
-    let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity) {
+    for (j, (_entity, name)) in items.iter().enumerate() {
         ctx.set(17, y, RGB::named(WHITE), RGB::named(BLACK), to_cp437('('));
         ctx.set(18, y, RGB::named(YELLOW), RGB::named(BLACK), 97 + j as FontCharType);
         ctx.set(19, y, RGB::named(WHITE), RGB::named(BLACK), to_cp437(')'));
 
-        ctx.print(21, y, &name.name.to_string());
-        equippable.push(entity);
+        ctx.print(21, y, name);
         y += 1;
-        j += 1;
     }
 
     match ctx.key {
@@ -141,7 +248,7 @@ pub fn item_menu(gs: &mut State, ctx: &mut BTerm, title: &str) -> ItemMenuResult
                 _ => {
                     let selection = letter_to_option(key);
                     if selection > -1 && selection < count as i32 {
-                        return ItemMenuResult::SelectedItem(equippable[selection as usize]);
+                        return ItemMenuResult::SelectedItem(items[selection as usize].0);
                     }
                     ItemMenuResult::NoResponse
                 }
@@ -158,6 +265,91 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut BTerm) -> ItemMenuResult {
     item_menu(gs, ctx, "Drop Which Item?")
 }
 
+pub fn throw_item_menu(gs: &mut State, ctx: &mut BTerm) -> ItemMenuResult {
+    item_menu(gs, ctx, "Throw Which Item?")
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum QuantityResult {
+    Cancel,
+    NoResponse,
+    Changed(i32),
+    Selected(i32),
+}
+
+/// Asks how many of a stack of `max` items to act on. The up and down keys change the amount,
+/// and Enter accepts it.
+pub fn quantity_menu(ctx: &mut BTerm, title: &str, quantity: i32, max: i32) -> QuantityResult {
+    ctx.draw_box(15, 23, 31, 4, RGB::named(WHITE), RGB::named(BLACK));
+    ctx.print_color(18, 23, RGB::named(YELLOW), RGB::named(BLACK), title);
+    ctx.print(18, 25, format!("< {} of {} >", quantity, max));
+    ctx.print_color(18, 27, RGB::named(YELLOW), RGB::named(BLACK), "ENTER to accept");
+
+    match ctx.key {
+        None => QuantityResult::NoResponse,
+        Some(key) => match key {
+            VirtualKeyCode::Escape => QuantityResult::Cancel,
+            VirtualKeyCode::Return => QuantityResult::Selected(quantity),
+            VirtualKeyCode::Up | VirtualKeyCode::Right | VirtualKeyCode::Numpad8 | VirtualKeyCode::Numpad6 | VirtualKeyCode::K | VirtualKeyCode::L => {
+                QuantityResult::Changed(i32::min(quantity + 1, max))
+            }
+            VirtualKeyCode::Down | VirtualKeyCode::Left | VirtualKeyCode::Numpad2 | VirtualKeyCode::Numpad4 | VirtualKeyCode::J | VirtualKeyCode::H => {
+                QuantityResult::Changed(i32::max(quantity - 1, 1))
+            }
+            _ => QuantityResult::NoResponse,
+        }
+    }
+}
+
+pub fn remove_item_menu(gs: &mut State, ctx: &mut BTerm) -> ItemMenuResult {
+    let items = {
+        let player_entity = gs.ecs.fetch::<Entity>();
+        let names = gs.ecs.read_storage::<Name>();
+        let equipped = gs.ecs.read_storage::<Equipped>();
+        let entities = gs.ecs.entities();
+
+        (&entities, &equipped, &names).join()
+            .filter(|item| item.1.owner == *player_entity)
+            .map(|(entity, _equipped, name)| (entity, name.name.to_string()))
+            .collect::<Vec<_>>()
+    };
+    item_list_menu(ctx, "Remove Which Item?", &items, None)
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ShopResult {
+    Cancel,
+    NoResponse,
+    SwitchMode,
+    Selected(Entity),
+}
+
+/// Lists what `vendor` has for sale, or what the player could sell them, with prices. TAB switches
+/// between buying and selling.
+pub fn shop_menu(gs: &mut State, ctx: &mut BTerm, vendor: Entity, mode: ShopMode) -> ShopResult {
+    if ctx.key == Some(VirtualKeyCode::Tab) { return ShopResult::SwitchMode; }
+
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let (owner, title, price): (Entity, &str, fn(&Value) -> i32) = match mode {
+        ShopMode::Buy => (vendor, "Buy (TAB to sell)", shop::buy_price),
+        ShopMode::Sell => (player_entity, "Sell (TAB to buy)", shop::sell_price),
+    };
+
+    let items: Vec<(Entity, String)> = {
+        let values = gs.ecs.read_storage::<Value>();
+        pack_contents(&gs.ecs, owner).into_iter()
+            .filter_map(|(item, description)| values.get(item).map(|value| (item, format!("{} - {} gp", description, price(value)))))
+            .collect()
+    };
+    let gold = gs.ecs.read_storage::<Wallet>().get(player_entity).map_or(0, |wallet| wallet.gold);
+
+    match item_list_menu(ctx, title, &items, Some(&format!("You have {} gold.", gold))) {
+        ItemMenuResult::Cancel => ShopResult::Cancel,
+        ItemMenuResult::NoResponse => ShopResult::NoResponse,
+        ItemMenuResult::SelectedItem(item) => ShopResult::Selected(item),
+    }
+}
+
 pub fn ranged_target(gs: &mut State, ctx: &mut BTerm, range: i32) -> TargetingResult {
     let player_entity = gs.ecs.fetch::<Entity>();
     let player_pos = gs.ecs.fetch::<Point>();
@@ -173,7 +365,9 @@ pub fn ranged_target(gs: &mut State, ctx: &mut BTerm, range: i32) -> TargetingRe
         for idx in visible.visible_tiles.iter() {
             let distance = DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
             if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, RGB::named(BLUE));
+                if let Some(screen) = map_to_screen(&gs.ecs, *idx) {
+                    ctx.set_bg(screen.x, screen.y, RGB::named(BLUE));
+                }
                 available_cells.push(idx);
             }
         }
@@ -183,14 +377,15 @@ pub fn ranged_target(gs: &mut State, ctx: &mut BTerm, range: i32) -> TargetingRe
 
     // Draw mouse cursor
     let mouse_pos = ctx.mouse_pos();
+    let mouse_map_pos = screen_to_map(&gs.ecs, Point::new(mouse_pos.0, mouse_pos.1));
     let mut valid_target = false;
     for idx in available_cells.iter() {
-        if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 { valid_target = true; }
+        if idx.x == mouse_map_pos.x && idx.y == mouse_map_pos.y { valid_target = true; }
     }
     if valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(CYAN));
         if ctx.left_click {
-            return TargetingResult::SelectedPoint(Point::new(mouse_pos.0, mouse_pos.1));
+            return TargetingResult::SelectedPoint(mouse_map_pos);
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(RED));
@@ -202,6 +397,70 @@ pub fn ranged_target(gs: &mut State, ctx: &mut BTerm, range: i32) -> TargetingRe
     TargetingResult::NoResponse
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum LookResult {
+    Cancel,
+    NoResponse,
+    Moved(Point),
+    Selected(Point),
+}
+
+/// Lets the player move a cursor around the map with the movement keys to see what is there.
+/// Pressing Enter picks the tile under the cursor.
+pub fn look_mode(gs: &mut State, ctx: &mut BTerm, cursor: Point) -> LookResult {
+    let map = gs.ecs.fetch::<Map>();
+    let names = gs.ecs.read_storage::<Name>();
+    let item_names = gs.ecs.fetch::<ItemNames>();
+    let positions = gs.ecs.read_storage::<Position>();
+
+    let idx = map.xy_idx(cursor.x, cursor.y);
+    let description = if !map.revealed_tiles[idx] {
+        "Unexplored".to_string()
+    } else {
+        let mut seen: Vec<String> = Vec::new();
+        if map.visible_tiles[idx] {
+            for (name, position) in (&names, &positions).join() {
+                if position.x == cursor.x && position.y == cursor.y {
+                    seen.push(item_names.describe(&name.name));
+                }
+            }
+        }
+        if seen.is_empty() {
+            match map.tiles[idx] {
+                TileType::Floor => "Floor".to_string(),
+                TileType::Wall => "Wall".to_string(),
+            }
+        } else {
+            seen.join(", ")
+        }
+    };
+    ctx.print_color(5, 0, RGB::named(YELLOW), RGB::named(BLACK), format!("Look: {} (Enter to travel)", description));
+    if let Some(screen) = map_to_screen(&gs.ecs, cursor) {
+        ctx.set_bg(screen.x, screen.y, RGB::named(CYAN));
+    }
+
+    let delta = match ctx.key {
+        None => return LookResult::NoResponse,
+        Some(key) => match key {
+            VirtualKeyCode::Escape => return LookResult::Cancel,
+            VirtualKeyCode::Return => return LookResult::Selected(cursor),
+            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => (-1, 0),
+            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => (1, 0),
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => (0, -1),
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => (0, 1),
+            VirtualKeyCode::Numpad9 | VirtualKeyCode::U => (1, -1),
+            VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => (-1, -1),
+            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => (1, 1),
+            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => (-1, 1),
+            _ => return LookResult::NoResponse,
+        }
+    };
+
+    let x = i32::clamp(cursor.x + delta.0, 0, map.width - 1);
+    let y = i32::clamp(cursor.y + delta.1, 0, map.height - 1);
+    LookResult::Moved(Point::new(x, y))
+}
+
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum MainMenuSelection { NewGame, LoadGame, Quit }
 
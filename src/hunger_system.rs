@@ -0,0 +1,55 @@
+use specs::prelude::*;
+use crate::components::{DamageType, HungerClock, HungerState, MyTurn, SufferDamage};
+use crate::gamelog::GameLog;
+
+/// How many turns each hunger state lasts before getting worse.
+pub const WELL_FED_TURNS: i32 = 200;
+const NORMAL_TURNS: i32 = 300;
+const HUNGRY_TURNS: i32 = 200;
+
+/// Ticks down the hunger clock of each creature taking a turn, moving it through the hunger
+/// states, and hurts anyone who is starving.
+pub struct HungerSystem {}
+
+impl<'a> System<'a> for HungerSystem {
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       WriteStorage<'a, HungerClock>,
+                       WriteStorage<'a, SufferDamage>,
+                       ReadStorage<'a, MyTurn>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player_entity, mut gamelog, mut hunger_clocks, mut suffer_damage, turns) = data;
+
+        for (entity, clock, _turn) in (&entities, &mut hunger_clocks, &turns).join() {
+            let is_player = entity == *player_entity;
+
+            clock.duration -= 1;
+            if clock.duration < 1 {
+                match clock.state {
+                    HungerState::WellFed => {
+                        clock.state = HungerState::Normal;
+                        clock.duration = NORMAL_TURNS;
+                        if is_player { gamelog.entries.push("You are no longer well fed.".to_string()); }
+                    }
+                    HungerState::Normal => {
+                        clock.state = HungerState::Hungry;
+                        clock.duration = HUNGRY_TURNS;
+                        if is_player { gamelog.entries.push("You are hungry.".to_string()); }
+                    }
+                    HungerState::Hungry => {
+                        clock.state = HungerState::Starving;
+                        clock.duration = 0;
+                        if is_player { gamelog.entries.push("You are starving!".to_string()); }
+                    }
+                    HungerState::Starving => {}
+                }
+            }
+
+            if clock.state == HungerState::Starving {
+                SufferDamage::new_damage(&mut suffer_damage, entity, 1, DamageType::Physical);
+            }
+        }
+    }
+}
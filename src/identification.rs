@@ -0,0 +1,81 @@
+use std::collections::{HashMap, HashSet};
+use bracket_lib::prelude::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+/// Magic items whose true nature is hidden until they are identified.
+const SCROLLS: &[&str] = &["Fireball Scroll", "Magic Missile Scroll", "Confusion Scroll", "Identify Scroll", "Remove Curse Scroll", "Recharging Scroll",
+    "Magic Mapping Scroll", "Teleport Scroll", "Blink Scroll", "Detect Monsters Scroll"];
+const POTIONS: &[&str] = &["Health Potion", "Confusion Potion", "Poison Potion", "Regeneration Potion", "Haste Potion"];
+const WANDS: &[&str] = &["Wand of Magic Missile", "Wand of Fire", "Wand of Confusion", "Wand of Digging", "Wand of Slowness", "Wand of Paralysis"];
+
+/// Cursed equipment passes for the ordinary kind until it is put on.
+const CURSED_EQUIPMENT: &[(&str, &str)] = &[
+    ("Longsword -2 (cursed)", "Longsword"),
+    ("Leather Armour -1 (cursed)", "Leather Armour"),
+];
+
+const SCROLL_SYLLABLES: &[&str] = &["ZEL", "GO", "MER", "XOR", "FOO", "KLA", "TU", "NEK", "RAH", "VOS", "ELB", "IB", "QUA", "DRA", "PHO"];
+const POTION_COLOURS: &[&str] = &["Murky", "Fizzy", "Golden", "Violet", "Smoky", "Cloudy", "Crimson", "Pearly", "Bubbling"];
+const WAND_WOODS: &[&str] = &["Oak", "Ash", "Yew", "Ebony", "Willow", "Bone", "Ivory"];
+
+/// What the player knows about magic items. Each run rolls new obscured names, so the player has to
+/// learn afresh what a "scroll of ZELGO MER" does.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ItemNames {
+    /// Maps an item's real name to what it is called until identified.
+    pub obscured: HashMap<String, String>,
+    /// Real names of the kinds of item that have been identified.
+    pub identified: HashSet<String>,
+}
+
+impl ItemNames {
+    /// Rolls obscured names for every kind of magic item.
+    pub fn new(rng: &mut RandomNumberGenerator) -> ItemNames {
+        let mut names = ItemNames::default();
+        let mut used = HashSet::new();
+
+        for scroll in SCROLLS {
+            let mut obscured = String::new();
+            while obscured.is_empty() || used.contains(&obscured) {
+                let words: Vec<String> = (0..2).map(|_| {
+                    (0..rng.range(1, 3)).map(|_| SCROLL_SYLLABLES[rng.range(0, SCROLL_SYLLABLES.len())]).collect()
+                }).collect();
+                obscured = format!("scroll of {}", words.join(" "));
+            }
+            used.insert(obscured.clone());
+            names.obscured.insert(scroll.to_string(), obscured);
+        }
+
+        let mut colours: Vec<&str> = POTION_COLOURS.to_vec();
+        for potion in POTIONS {
+            let colour = colours.remove(rng.range(0, colours.len()));
+            names.obscured.insert(potion.to_string(), format!("{} Potion", colour));
+        }
+
+        let mut woods: Vec<&str> = WAND_WOODS.to_vec();
+        for wand in WANDS {
+            let wood = woods.remove(rng.range(0, woods.len()));
+            names.obscured.insert(wand.to_string(), format!("{} Wand", wood));
+        }
+
+        for (real, looks_like) in CURSED_EQUIPMENT {
+            names.obscured.insert(real.to_string(), looks_like.to_string());
+        }
+
+        names
+    }
+
+    /// The name the player knows something by: its real name once identified, or if it was never
+    /// a mystery, and its obscured name otherwise.
+    pub fn describe(&self, real_name: &str) -> String {
+        match self.obscured.get(real_name) {
+            Some(obscured) if !self.identified.contains(real_name) => obscured.clone(),
+            _ => real_name.to_string(),
+        }
+    }
+
+    /// Marks a kind of item as identified. Returns true if it wasn't already.
+    pub fn identify(&mut self, real_name: &str) -> bool {
+        self.obscured.contains_key(real_name) && self.identified.insert(real_name.to_string())
+    }
+}
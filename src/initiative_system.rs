@@ -0,0 +1,55 @@
+use specs::prelude::*;
+use crate::components::{Encumbered, Energy, MyTurn, StatusEffects, StatusKind};
+use crate::RunState;
+
+/// Energy a creature spends to take one turn.
+pub const ACTION_COST: i32 = 100;
+
+/// Speed of an ordinary creature, which gets one turn for every ten ticks.
+pub const NORMAL_SPEED: i32 = 10;
+
+/// Advances time by one tick. Every creature gains energy according to its speed, and any that
+/// have saved up enough are given a turn this tick. Reaching the player's turn stops the clock
+/// until they have decided what to do.
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, RunState>,
+                       WriteStorage<'a, Energy>,
+                       WriteStorage<'a, MyTurn>,
+                       ReadStorage<'a, StatusEffects>,
+                       ReadStorage<'a, Encumbered>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player_entity, mut runstate, mut energies, mut turns, status_effects, encumbered) = data;
+
+        if *runstate != RunState::Ticking { return; }
+
+        turns.clear();
+
+        for (entity, energy) in (&entities, &mut energies).join() {
+            energy.current += effective_speed(energy.speed, status_effects.get(entity), encumbered.get(entity).is_some());
+            if energy.current >= ACTION_COST {
+                energy.current -= ACTION_COST;
+                turns.insert(entity, MyTurn {}).expect("Unable to insert turn");
+
+                if entity == *player_entity {
+                    *runstate = RunState::AwaitingInput;
+                }
+            }
+        }
+    }
+}
+
+/// Haste doubles a creature's speed and slow halves it. Being encumbered halves it again.
+fn effective_speed(speed: i32, statuses: Option<&StatusEffects>, encumbered: bool) -> i32 {
+    let speed = match statuses {
+        Some(statuses) if statuses.has(StatusKind::Haste) => speed * 2,
+        Some(statuses) if statuses.has(StatusKind::Slow) => speed / 2,
+        _ => speed,
+    };
+    if encumbered { speed / 2 } else { speed }
+}
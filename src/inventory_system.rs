@@ -1,39 +1,303 @@
+use std::ops::Deref;
 use bracket_lib::prelude::*;
-use specs::{Entities, Entity, Join, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
-use crate::components::{CombatStats, Consumable, InBackpack, Name, Position, ProvidesHealing, WantsToUseItem, WantsToDropItem, InflictsDamage, SufferDamage, AreaOfEffect, Confusion};
+use specs::{Entities, Entity, Join, ReadExpect, ReadStorage, Storage, System, World, WorldExt, WriteExpect, WriteStorage};
+use specs::storage::MaskedStorage;
+use crate::components::{CombatStats, Consumable, InBackpack, Name, Position, ProvidesHealing, WantsToUseItem, WantsToDropItem, InflictsDamage, SufferDamage, AreaOfEffect, InflictsStatus, StatusEffects, Equippable, Equipped, WantsToRemoveItem, ProvidesFood, HungerClock, HungerState, Identifies, Cursed, RemovesCurse, CURSED_SUFFIX, Charges, Recharges, Stackable, MagicMapper, TeleportsUser, Blinks, Digs, Viewshed};
+use crate::hunger_system::WELL_FED_TURNS;
+use crate::identification::ItemNames;
 use crate::gamelog::GameLog;
-use crate::map::Map;
+use crate::map::{Map, TileType};
+use crate::noise::{Noise, Noises};
+use crate::pathing_map_system::PathingMaps;
+use crate::RunState;
+
+/// How far away a damaging spell going off can be heard.
+const BLAST_NOISE_RADIUS: f32 = 10.0;
+
+/// Puts on items that have been "used" from the backpack, in place of whatever was in their slot.
+/// Runs before `ItemUseSystem`, and takes the intents it deals with out of its way.
+pub struct ItemEquipSystem {}
+
+impl<'a> System<'a> for ItemEquipSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       ReadExpect<'a, Map>,
+                       WriteExpect<'a, ItemNames>,
+                       Entities<'a>,
+                       WriteStorage<'a, WantsToUseItem>,
+                       ReadStorage<'a, Name>,
+                       ReadStorage<'a, Position>,
+                       ReadStorage<'a, Equippable>,
+                       WriteStorage<'a, Equipped>,
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, Cursed>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut gamelog, map, mut item_names, entities, mut wants_use, names, positions, equippable, mut equipped, mut backpack, cursed) = data;
+
+        let mut handled = Vec::new();
+        for (entity, useitem) in (&entities, &wants_use).join() {
+            let Some(can_equip) = equippable.get(useitem.item) else { continue };
+            handled.push(entity);
+
+            let slot = can_equip.slot;
+            let user_name = &names.get(entity).unwrap().name;
+            let item_name = &names.get(useitem.item).unwrap().name;
+            let user_seen = entity != *player_entity
+                && positions.get(entity).is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)]);
+
+            // Cursed gear won't come off to make room
+            let in_slot: Vec<Entity> = (&entities, &equipped).join()
+                .filter(|(_item, already)| already.owner == entity && already.slot == slot)
+                .map(|(item, _already)| item)
+                .collect();
+            if let Some(stuck) = in_slot.iter().find(|item| cursed.get(**item).is_some()) {
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You can't take off the {}; it's cursed!", names.get(*stuck).unwrap().name));
+                }
+                continue;
+            }
+
+            for item in in_slot {
+                equipped.remove(item);
+                backpack.insert(item, InBackpack { owner: entity }).expect("Unable to insert backpack entry");
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You unequip {}.", item_names.describe(&names.get(item).unwrap().name)));
+                }
+            }
+            equipped.insert(useitem.item, Equipped { owner: entity, slot }).expect("Unable to equip desired item");
+            backpack.remove(useitem.item);
+
+            let obscured_name = item_names.describe(item_name);
+            if entity == *player_entity {
+                gamelog.entries.push(format!("You equip {}.", obscured_name));
+            } else if user_seen {
+                gamelog.entries.push(format!("{} equips {}.", user_name, obscured_name));
+            }
+
+            // Putting something on is the surest way to find out what it really is
+            if (entity == *player_entity || user_seen) && item_names.identify(item_name) {
+                gamelog.entries.push(format!("The {} turns out to be a {}.", obscured_name, item_name));
+            }
+            if cursed.get(useitem.item).is_some() && entity == *player_entity {
+                gamelog.entries.push(format!("The {} clamps onto you. It's cursed!", item_name));
+            }
+        }
+
+        for entity in handled {
+            wants_use.remove(entity);
+        }
+    }
+}
+
+/// Spends a charge from each charged item about to be used, and stops empty ones from doing
+/// anything. Also carries out Recharge scrolls. Runs before `ItemUseSystem`.
+pub struct ItemChargeSystem {}
+
+impl<'a> System<'a> for ItemChargeSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       ReadExpect<'a, ItemNames>,
+                       Entities<'a>,
+                       WriteStorage<'a, WantsToUseItem>,
+                       ReadStorage<'a, Name>,
+                       WriteStorage<'a, Charges>,
+                       ReadStorage<'a, Recharges>,
+                       ReadStorage<'a, InBackpack>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut gamelog, item_names, entities, mut wants_use, names, mut charges, recharges, backpack) = data;
+
+        let mut fizzled = Vec::new();
+        for (entity, useitem) in (&entities, &wants_use).join() {
+            if let Some(item_charges) = charges.get_mut(useitem.item) {
+                if item_charges.current < 1 {
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!("The {} is out of charges.", item_names.describe(&names.get(useitem.item).unwrap().name)));
+                    }
+                    fizzled.push(entity);
+                } else {
+                    item_charges.current -= 1;
+                }
+            }
+
+            if recharges.get(useitem.item).is_some() {
+                for (item, _pack) in (&entities, &backpack).join().filter(|(_item, pack)| pack.owner == entity) {
+                    if let Some(item_charges) = charges.get_mut(item) {
+                        item_charges.current = item_charges.max;
+                        if entity == *player_entity {
+                            gamelog.entries.push(format!("The {} hums with renewed power.", item_names.describe(&names.get(item).unwrap().name)));
+                        }
+                    }
+                }
+            }
+        }
+
+        for entity in fizzled {
+            wants_use.remove(entity);
+        }
+    }
+}
+
+/// Carries out the items that move their user or change the map: magic mapping, teleportation,
+/// blinking and digging. Runs before `ItemUseSystem`, which still identifies and uses up the item.
+pub struct ItemUtilitySystem {}
+
+impl<'a> System<'a> for ItemUtilitySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, Map>,
+                       WriteExpect<'a, RunState>,
+                       WriteExpect<'a, RandomNumberGenerator>,
+                       WriteExpect<'a, Point>,
+                       WriteExpect<'a, PathingMaps>,
+                       Entities<'a>,
+                       ReadStorage<'a, WantsToUseItem>,
+                       WriteStorage<'a, Position>,
+                       WriteStorage<'a, Viewshed>,
+                       ReadStorage<'a, MagicMapper>,
+                       ReadStorage<'a, TeleportsUser>,
+                       ReadStorage<'a, Blinks>,
+                       ReadStorage<'a, Digs>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut gamelog, mut map, mut runstate, mut rng, mut player_pos, mut pathing, entities, wants_use, mut positions, mut viewsheds,
+            magic_mappers, teleports, blinks, digs) = data;
+
+        for (entity, useitem) in (&entities, &wants_use).join() {
+            let is_player = entity == *player_entity;
+            let mut destination = None;
+
+            if magic_mappers.get(useitem.item).is_some() && is_player {
+                gamelog.entries.push("The layout of the level floods into your mind!".to_string());
+                // The initiative system may already have handed the player their next turn
+                *runstate = RunState::MagicMapReveal { row: 0, awaiting_input: *runstate == RunState::AwaitingInput };
+            }
+
+            if teleports.get(useitem.item).is_some() {
+                loop {
+                    let idx = rng.range(0, map.tiles.len());
+                    if map.tiles[idx] == TileType::Floor && !map.blocked[idx] {
+                        destination = Some(Point::new(idx as i32 % map.width, idx as i32 / map.width));
+                        break;
+                    }
+                }
+                if is_player {
+                    gamelog.entries.push("The world spins, and you find yourself somewhere else.".to_string());
+                }
+            }
+
+            if let (Some(_), Some(target)) = (blinks.get(useitem.item), useitem.target) {
+                let idx = map.xy_idx(target.x, target.y);
+                if map.tiles[idx] == TileType::Floor && !map.blocked[idx] {
+                    destination = Some(target);
+                } else if is_player {
+                    gamelog.entries.push("Something is in the way.".to_string());
+                }
+            }
+
+            if let (Some(_), Some(target), Some(pos)) = (digs.get(useitem.item), useitem.target, positions.get(entity)) {
+                let start = Point::new(pos.x, pos.y);
+                let mut dug = 0;
+                for point in line2d(LineAlg::Bresenham, start, target).into_iter().filter(|point| *point != start) {
+                    // The edge of the map stays solid
+                    if point.x < 1 || point.x > map.width - 2 || point.y < 1 || point.y > map.height - 2 { break; }
+                    let idx = map.xy_idx(point.x, point.y);
+                    if map.tiles[idx] == TileType::Wall {
+                        map.tiles[idx] = TileType::Floor;
+                        dug += 1;
+                    }
+                }
+                if dug > 0 {
+                    for viewshed in (&mut viewsheds).join() {
+                        viewshed.dirty = true;
+                    }
+                    pathing.invalidate();
+                }
+                if is_player {
+                    gamelog.entries.push(format!("You dig through {} section{} of wall.", dug, if dug == 1 { "" } else { "s" }));
+                }
+            }
+
+            if let Some(destination) = destination {
+                if let Some(pos) = positions.get_mut(entity) {
+                    pos.x = destination.x;
+                    pos.y = destination.y;
+                }
+                if let Some(viewshed) = viewsheds.get_mut(entity) {
+                    viewshed.dirty = true;
+                }
+                if is_player {
+                    *player_pos = destination;
+                }
+            }
+        }
+    }
+}
 
 pub struct ItemUseSystem {}
 
 impl<'a> System<'a> for ItemUseSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (ReadExpect<'a, Entity>,
                        WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, Noises>,
                        ReadExpect<'a, Map>,
                        Entities<'a>,
                        WriteStorage<'a, WantsToUseItem>,
-                       ReadStorage<'a, Name>,
+                       WriteStorage<'a, Name>,
                        ReadStorage<'a, Consumable>,
                        ReadStorage<'a, ProvidesHealing>,
                        ReadStorage<'a, InflictsDamage>,
                        WriteStorage<'a, CombatStats>,
                        WriteStorage<'a, SufferDamage>,
                        ReadStorage<'a, AreaOfEffect>,
-                       WriteStorage<'a, Confusion>
+                       ReadStorage<'a, InflictsStatus>,
+                       WriteStorage<'a, StatusEffects>,
+                       ReadStorage<'a, Position>,
+                       ReadStorage<'a, Equipped>,
+                       ReadStorage<'a, InBackpack>,
+                       ReadStorage<'a, ProvidesFood>,
+                       WriteStorage<'a, HungerClock>,
+                       WriteExpect<'a, ItemNames>,
+                       ReadStorage<'a, Identifies>,
+                       WriteStorage<'a, Cursed>,
+                       ReadStorage<'a, RemovesCurse>
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, map, entities, wants_use, names, consumables, healing, inflict_damage, mut combat_stats, mut suffer_damage, aoe, mut confused) = data;
+        let (player_entity, mut gamelog, mut noises, map, entities, mut wants_use, mut names, consumables, healing, inflict_damage, mut combat_stats, mut suffer_damage, aoe, inflicts_status, mut status_effects, positions, equipped, backpack, food, mut hunger_clocks, mut item_names, identifies, mut cursed, removes_curse) = data;
+
+        let mut uncursed = Vec::new();
 
         for (entity, useitem) in (&entities, &wants_use).join() {
             let mut used_item = true;
+            let user_name = &names.get(entity).unwrap().name;
+            let item_name = &names.get(useitem.item).unwrap().name;
+            // Only report what monsters get up to when the player can see them, or is on the receiving end
+            let user_seen = entity != *player_entity
+                && positions.get(entity).is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)]);
+
+            // Using a magic item, or watching it being used, reveals what it is
+            if entity == *player_entity || user_seen {
+                let obscured_name = item_names.describe(item_name);
+                if item_names.identify(item_name) {
+                    gamelog.entries.push(format!("The {} turns out to be a {}.", obscured_name, item_name));
+                }
+            }
 
             // Targeting
             let mut targets = Vec::new();
             match useitem.target {
                 None => {
-                    // Target is just the player
-                    targets.push(*player_entity);
+                    // Target is whoever is using the item
+                    targets.push(entity);
                 }
                 Some(target) => {
                     let area_effect = aoe.get(useitem.item);
@@ -60,6 +324,60 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
+            // Identify scrolls reveal everything the reader is carrying
+            if identifies.get(useitem.item).is_some() {
+                let carried: Vec<Entity> = (&entities, &backpack).join()
+                    .filter(|(item, pack)| pack.owner == entity && *item != useitem.item)
+                    .map(|(item, _pack)| item)
+                    .collect();
+                for item in carried {
+                    let real_name = &names.get(item).unwrap().name;
+                    let obscured_name = item_names.describe(real_name);
+                    if item_names.identify(real_name) && entity == *player_entity {
+                        gamelog.entries.push(format!("You identify the {} as a {}.", obscured_name, real_name));
+                    }
+                }
+            }
+
+            // Remove Curse scrolls lift the curse from everything the reader has on them
+            if removes_curse.get(useitem.item).is_some() {
+                let belongings: Vec<Entity> = (&entities, &backpack).join()
+                    .filter(|(_item, pack)| pack.owner == entity)
+                    .map(|(item, _pack)| item)
+                    .chain((&entities, &equipped).join()
+                        .filter(|(_item, equipped)| equipped.owner == entity)
+                        .map(|(item, _equipped)| item))
+                    .filter(|item| cursed.get(*item).is_some())
+                    .collect();
+                if belongings.is_empty() && entity == *player_entity {
+                    gamelog.entries.push("You feel as if someone is watching over you.".to_string());
+                }
+                for item in belongings {
+                    cursed.remove(item);
+                    let real_name = &names.get(item).unwrap().name;
+                    item_names.identify(real_name);
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!("The {} glows softly. Its curse is lifted.", real_name));
+                    }
+                    uncursed.push(item);
+                }
+            }
+
+            // Food resets the eater's hunger clock
+            if food.get(useitem.item).is_some() {
+                for target in targets.iter() {
+                    if let Some(clock) = hunger_clocks.get_mut(*target) {
+                        clock.state = HungerState::WellFed;
+                        clock.duration = WELL_FED_TURNS;
+                    }
+                }
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You eat the {}.", item_name));
+                } else if user_seen {
+                    gamelog.entries.push(format!("{} eats the {}.", user_name, item_name));
+                }
+            }
+
             // if it heals, apply the healing
             let item_heals = healing.get(useitem.item);
             match item_heals {
@@ -70,7 +388,9 @@ impl<'a> System<'a> for ItemUseSystem {
                         if let Some(stats) = stats {
                             stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
                             if entity == *player_entity {
-                                gamelog.entries.push(format!("You drink the {}, healing {} hp.", names.get(useitem.item).unwrap().name, healer.heal_amount));
+                                gamelog.entries.push(format!("You drink the {}, healing {} hp.", item_name, healer.heal_amount));
+                            } else if user_seen {
+                                gamelog.entries.push(format!("{} drinks a {}.", user_name, item_name));
                             }
                         }
                     }
@@ -83,12 +403,19 @@ impl<'a> System<'a> for ItemUseSystem {
                 None => {}
                 Some(damage) => {
                     used_item = false;
+                    if let Some(target) = useitem.target {
+                        noises.sources.push(Noise { pos: target, radius: BLAST_NOISE_RADIUS });
+                    }
                     for mob in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
+                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage, damage.damage_type);
+                        let mob_name = &names.get(*mob).unwrap().name;
                         if entity == *player_entity {
-                            let mob_name = names.get(*mob).unwrap();
-                            let item_name = names.get(useitem.item).unwrap();
-                            gamelog.entries.push(format!("You use {} on {}, inflicting {} damage.", item_name.name, mob_name.name, damage.damage));
+                            gamelog.entries.push(format!("You use {} on {}.", item_name, mob_name));
+                        } else if user_seen {
+                            gamelog.entries.push(format!("{} uses {} on {}.", user_name, item_name, mob_name));
+                        } else if *mob == *player_entity {
+                            // Never leave the player wondering where the damage came from
+                            gamelog.entries.push(format!("Something unseen uses {} on you.", item_names.describe(item_name)));
                         }
 
                         used_item = true;
@@ -96,30 +423,27 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
-            // Can it pass along confusion?
-            let mut add_confusion = Vec::new();
-            {
-                let causes_confusion = confused.get(useitem.item);
-                match causes_confusion {
-                    None => {}
-                    Some(confusion) => {
-                        used_item = false;
-                        for mob in targets.iter() {
-                            add_confusion.push((*mob, confusion.turns));
-                            if entity == *player_entity {
-                                let mob_name = names.get(*mob).unwrap();
-                                let item_name = names.get(useitem.item).unwrap();
-                                gamelog.entries.push(format!("You use {} on {}, confusing them.", item_name.name, mob_name.name));
-                            }
-
-                            used_item = true;
+            // Can it pass along a status effect?
+            let item_inflicts_status = inflicts_status.get(useitem.item);
+            match item_inflicts_status {
+                None => {}
+                Some(inflicts) => {
+                    used_item = false;
+                    for mob in targets.iter() {
+                        StatusEffects::add_effect(&mut status_effects, *mob, inflicts.effect);
+                        let mob_name = &names.get(*mob).unwrap().name;
+                        if entity == *player_entity {
+                            gamelog.entries.push(format!("You use {} on {}, who is now {}.", item_name, mob_name, inflicts.effect.kind.adjective()));
+                        } else if user_seen {
+                            gamelog.entries.push(format!("{} uses {} on {}, who is now {}.", user_name, item_name, mob_name, inflicts.effect.kind.adjective()));
+                        } else if *mob == *player_entity {
+                            gamelog.entries.push(format!("Something unseen uses {} on you, and you are now {}.", item_names.describe(item_name), inflicts.effect.kind.adjective()));
                         }
+
+                        used_item = true;
                     }
                 }
             }
-            for mob in add_confusion.iter() {
-                confused.insert(mob.0, Confusion { turns: mob.1 }).expect("Unable to insert status");
-            }
 
             // If it's a consumable, we delete it on use
             if used_item {
@@ -128,6 +452,42 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
         }
+
+        wants_use.clear();
+
+        // Once the curse is gone, so is the warning in the item's name
+        for item in uncursed {
+            if let Some(name) = names.get_mut(item) {
+                name.name = name.name.trim_end_matches(CURSED_SUFFIX).to_string();
+            }
+        }
+    }
+}
+
+/// Everything in `owner`'s backpack that makes up the stack of items called `name`: the stackable
+/// items with that name.
+pub fn carried_stack<D>(entities: &Entities, backpack: &Storage<'_, InBackpack, D>, names: &ReadStorage<'_, Name>, stackable: &ReadStorage<'_, Stackable>,
+                        owner: Entity, name: &str) -> Vec<Entity>
+    where D: Deref<Target = MaskedStorage<InBackpack>>
+{
+    (entities, backpack, names, stackable).join()
+        .filter(|(_item, pack, item_name, _stackable)| pack.owner == owner && item_name.name == name)
+        .map(|(item, _pack, _name, _stackable)| item)
+        .collect()
+}
+
+/// How many items are in the stack `item` belongs to, counting itself.
+pub fn stack_size(ecs: &World, item: Entity) -> i32 {
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let stackable = ecs.read_storage::<Stackable>();
+    let entities = ecs.entities();
+
+    match (backpack.get(item), stackable.get(item)) {
+        (Some(carried), Some(_)) => {
+            carried_stack(&entities, &backpack, &names, &stackable, carried.owner, &names.get(item).unwrap().name).len() as i32
+        }
+        _ => 1,
     }
 }
 
@@ -141,22 +501,88 @@ impl<'a> System<'a> for ItemDropSystem {
                        WriteStorage<'a, WantsToDropItem>,
                        ReadStorage<'a, Name>,
                        WriteStorage<'a, Position>,
-                       WriteStorage<'a, InBackpack>
+                       WriteStorage<'a, InBackpack>,
+                       WriteExpect<'a, ItemNames>,
+                       ReadStorage<'a, Cursed>,
+                       ReadStorage<'a, Stackable>,
+                       ReadStorage<'a, Equipped>
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, entities, mut wants_drop, names, mut positions, mut backpack) = data;
+        let (player_entity, mut gamelog, entities, mut wants_drop, names, mut positions, mut backpack, mut item_names, cursed, stackable, equipped) = data;
 
         for (entity, to_drop) in (&entities, &wants_drop).join() {
+            if cursed.get(to_drop.item).is_some() && equipped.get(to_drop.item).is_some() {
+                // Trying to get rid of it is enough to give the game away
+                let real_name = &names.get(to_drop.item).unwrap().name;
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You can't let go of the {}; it's cursed!", item_names.describe(real_name)));
+                    item_names.identify(real_name);
+                }
+                continue;
+            }
+
+            // Drop the chosen item, and make up the quantity from the rest of its stack
+            let name = &names.get(to_drop.item).unwrap().name;
+            let mut dropped = vec![to_drop.item];
+            if stackable.get(to_drop.item).is_some() {
+                dropped.extend(carried_stack(&entities, &backpack, &names, &stackable, entity, name).into_iter()
+                    .filter(|item| *item != to_drop.item)
+                    .take(to_drop.quantity as usize - 1));
+            }
+
             let dropper_pos = *positions.get(entity).unwrap();
-            positions.insert(to_drop.item, dropper_pos).expect("Unable to insert position");
-            backpack.remove(to_drop.item);
+            for item in dropped.iter() {
+                positions.insert(*item, dropper_pos).expect("Unable to insert position");
+                backpack.remove(*item);
+            }
 
             if entity == *player_entity {
-                gamelog.entries.push(format!("You drop the {}.", names.get(to_drop.item).unwrap().name));
+                if dropped.len() > 1 {
+                    gamelog.entries.push(format!("You drop the {} (x{}).", item_names.describe(name), dropped.len()));
+                } else {
+                    gamelog.entries.push(format!("You drop the {}.", item_names.describe(name)));
+                }
             }
         }
 
         wants_drop.clear();
     }
 }
+
+pub struct ItemRemoveSystem {}
+
+impl<'a> System<'a> for ItemRemoveSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       Entities<'a>,
+                       WriteStorage<'a, WantsToRemoveItem>,
+                       ReadStorage<'a, Name>,
+                       WriteStorage<'a, Equipped>,
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, Cursed>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut gamelog, entities, mut wants_remove, names, mut equipped, mut backpack, cursed) = data;
+
+        for (entity, to_remove) in (&entities, &wants_remove).join() {
+            if cursed.get(to_remove.item).is_some() {
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You can't remove the {}; it's cursed!", names.get(to_remove.item).unwrap().name));
+                }
+                continue;
+            }
+
+            equipped.remove(to_remove.item);
+            backpack.insert(to_remove.item, InBackpack { owner: entity }).expect("Unable to insert backpack entry");
+
+            if entity == *player_entity {
+                gamelog.entries.push(format!("You unequip {}.", names.get(to_remove.item).unwrap().name));
+            }
+        }
+
+        wants_remove.clear();
+    }
+}
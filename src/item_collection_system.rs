@@ -1,26 +1,94 @@
 use specs::prelude::*;
-use crate::components::{InBackpack, Name, Position, WantsToPickupItem};
+use crate::components::{Attributes, Equipped, Gold, InBackpack, Name, Position, Stackable, Wallet, WantsToPickupItem, Weight};
+use crate::encumbrance_system::{weigh_load, MAX_LOAD_MULTIPLIER};
 use crate::gamelog::GameLog;
+use crate::identification::ItemNames;
+use crate::inventory_system::carried_stack;
 
 pub struct ItemCollectionSystem {}
 
 impl<'a> System<'a> for ItemCollectionSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (ReadExpect<'a, Entity>,
                        WriteExpect<'a, GameLog>,
+                       ReadExpect<'a, ItemNames>,
+                       Entities<'a>,
                        WriteStorage<'a, WantsToPickupItem>,
                        WriteStorage<'a, Position>,
                        ReadStorage<'a, Name>,
-                       WriteStorage<'a, InBackpack>);
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, Stackable>,
+                       ReadStorage<'a, Equipped>,
+                       ReadStorage<'a, Weight>,
+                       ReadStorage<'a, Attributes>,
+                       ReadStorage<'a, Gold>,
+                       WriteStorage<'a, Wallet>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) = data;
+        let (player_entity, mut gamelog, item_names, entities, mut wants_pickup, mut positions, names, mut backpack, stackable,
+            equipped, weights, attributes, gold, mut wallets) = data;
 
+        // Picking up part of a stack picks up the rest of it lying on the same tile
+        let mut pickups: Vec<(Entity, Vec<Entity>)> = Vec::new();
         for pickup in wants_pickup.join() {
-            positions.remove(pickup.item);
-            backpack.insert(pickup.item, InBackpack{ owner: pickup.collected_by }).expect("Unable to insert backpack entry");
+            let mut items = vec![pickup.item];
+            if let (Some(_), Some(pos)) = (stackable.get(pickup.item), positions.get(pickup.item)) {
+                let name = &names.get(pickup.item).unwrap().name;
+                items.extend((&entities, &positions, &names, &stackable).join()
+                    .filter(|(item, item_pos, item_name, _stackable)| *item != pickup.item && item_pos.x == pos.x && item_pos.y == pos.y && item_name.name == *name)
+                    .map(|(item, _pos, _name, _stackable)| item));
+            }
+            pickups.push((pickup.collected_by, items));
+        }
+
+        for (collected_by, items) in pickups {
+            let name = &names.get(items[0]).unwrap().name;
+
+            // Coins go in the wallet rather than the backpack
+            if let (Some(gold), Some(wallet)) = (gold.get(items[0]), wallets.get_mut(collected_by)) {
+                wallet.gold += gold.amount;
+                entities.delete(items[0]).expect("Delete failed");
+                if collected_by == *player_entity {
+                    gamelog.entries.push(format!("You pick up {} gold.", gold.amount));
+                }
+                continue;
+            }
+
+            let is_stackable = stackable.get(items[0]).is_some();
+            let mut picked_up = 0;
+            for item in items {
+                if let Some(attributes) = attributes.get(collected_by) {
+                    let carried = weigh_load(&backpack, &equipped, &weights, collected_by);
+                    let item_weight = weights.get(item).map_or(0.0, |weight| weight.lbs);
+                    if carried + item_weight > Attributes::carry_capacity(attributes.might) * MAX_LOAD_MULTIPLIER {
+                        if collected_by == *player_entity {
+                            gamelog.entries.push(format!("The {} is too heavy to carry with everything else.", item_names.describe(name)));
+                        }
+                        break;
+                    }
+                }
+
+                positions.remove(item);
+                backpack.insert(item, InBackpack{ owner: collected_by }).expect("Unable to insert backpack entry");
+                picked_up += 1;
+            }
 
-            if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!("You pick up the {}.", names.get(pickup.item).unwrap().name))
+            if collected_by == *player_entity && picked_up > 0 {
+                let stack_size = if is_stackable {
+                    carried_stack(&entities, &backpack, &names, &stackable, collected_by, name).len()
+                } else {
+                    1
+                };
+                let description = item_names.describe(name);
+                if picked_up == 1 && stack_size > 1 {
+                    gamelog.entries.push(format!("You pick up the {} (now {}).", description, stack_size))
+                } else if stack_size > picked_up {
+                    gamelog.entries.push(format!("You pick up the {} (x{}, now {}).", description, picked_up, stack_size))
+                } else if picked_up > 1 {
+                    gamelog.entries.push(format!("You pick up the {} (x{}).", description, picked_up))
+                } else {
+                    gamelog.entries.push(format!("You pick up the {}.", description))
+                }
             }
         }
 
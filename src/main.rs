@@ -12,21 +12,40 @@ mod spawner;
 mod item_collection_system;
 mod inventory_system;
 mod saveload_system;
+mod camera;
+mod status_effect_system;
+mod hunger_system;
+mod identification;
+mod initiative_system;
+mod pathing_map_system;
+mod noise;
+mod faction;
+mod encumbrance_system;
+mod throwing_system;
+mod shop;
+mod ranged_combat_system;
 
 use bracket_lib::prelude::*;
 use specs::prelude::*;
 use specs::saveload::SimpleMarkerAllocator;
 use components::*;
 use crate::damage_system::DamageSystem;
-use crate::gui::{ItemMenuResult, MainMenuResult, MainMenuSelection, TargetingResult};
-use crate::inventory_system::{ItemDropSystem, ItemUseSystem};
+use crate::encumbrance_system::EncumbranceSystem;
+use crate::gui::{ItemMenuResult, LookResult, MainMenuResult, MainMenuSelection, QuantityResult, ShopResult, TargetingResult};
+use crate::initiative_system::InitiativeSystem;
+use crate::inventory_system::{stack_size, ItemChargeSystem, ItemDropSystem, ItemEquipSystem, ItemRemoveSystem, ItemUseSystem, ItemUtilitySystem};
 use crate::item_collection_system::ItemCollectionSystem;
 use crate::map::*;
 use crate::map_indexing_system::MapIndexingSystem;
 use crate::melee_combat_system::MeleeCombatSystem;
-use crate::monster_ai_system::MonsterAI;
-use crate::player::player_input;
+use crate::monster_ai_system::{MonsterAI, MonsterItemAI};
+use crate::pathing_map_system::{PathingMapSystem, PathingMaps};
+use crate::ranged_combat_system::RangedCombatSystem;
+use crate::player::{player_input, start_travel, AutoPilot};
 use crate::spawner::confusion_scroll;
+use crate::status_effect_system::StatusEffectSystem;
+use crate::throwing_system::{ThrowingSystem, THROW_RANGE};
+use crate::hunger_system::HungerSystem;
 use crate::visibility_system::VisibilitySystem;
 
 const TERM_WIDTH: i32 = 80;
@@ -36,10 +55,16 @@ const TERM_HEIGHT: i32 = 50;
 pub enum RunState {
     AwaitingInput,
     PreRun,
-    PlayerTurn,
-    MonsterTurn,
+    Ticking,
     ItemMenu(ItemMenuOp),
     ShowTargeting { range: i32, item: Entity },
+    ThrowTargeting { item: Entity },
+    FireTargeting { range: i32 },
+    DropQuantity { item: Entity, quantity: i32, max: i32 },
+    Shop { vendor: Entity, mode: ShopMode },
+    /// `awaiting_input` remembers whether it became the player's turn in the tick the map was read.
+    MagicMapReveal { row: i32, awaiting_input: bool },
+    Look { cursor: Point },
     MainMenu { menu_selection: MainMenuSelection },
     SaveGame
 }
@@ -48,6 +73,14 @@ pub enum RunState {
 pub enum ItemMenuOp {
     Use,
     Drop,
+    Remove,
+    Throw,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ShopMode {
+    Buy,
+    Sell,
 }
 
 struct State {
@@ -56,22 +89,46 @@ struct State {
 
 impl State {
     fn run_systems(&mut self) {
+        let mut initiative = InitiativeSystem {};
+        initiative.run_now(&self.ecs);
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
+        let mut pathing = PathingMapSystem {};
+        pathing.run_now(&self.ecs);
+        let mut mob_items = MonsterItemAI {};
+        mob_items.run_now(&self.ecs);
         let mut mob = MonsterAI {};
         mob.run_now(&self.ecs);
+        let mut status_effects = StatusEffectSystem {};
+        status_effects.run_now(&self.ecs);
+        let mut hunger = HungerSystem {};
+        hunger.run_now(&self.ecs);
         let mut mapindex = MapIndexingSystem {};
         mapindex.run_now(&self.ecs);
         let mut melee_combat_system = MeleeCombatSystem {};
         melee_combat_system.run_now(&self.ecs);
+        let mut ranged_combat_system = RangedCombatSystem {};
+        ranged_combat_system.run_now(&self.ecs);
         let mut damage_system = DamageSystem {};
         damage_system.run_now(&self.ecs);
         let mut pickup = ItemCollectionSystem {};
         pickup.run_now(&self.ecs);
+        let mut equip_items = ItemEquipSystem {};
+        equip_items.run_now(&self.ecs);
+        let mut charged_items = ItemChargeSystem {};
+        charged_items.run_now(&self.ecs);
+        let mut utility_items = ItemUtilitySystem {};
+        utility_items.run_now(&self.ecs);
         let mut potions = ItemUseSystem {};
         potions.run_now(&self.ecs);
         let mut drop_items = ItemDropSystem {};
         drop_items.run_now(&self.ecs);
+        let mut remove_items = ItemRemoveSystem {};
+        remove_items.run_now(&self.ecs);
+        let mut throw_items = ThrowingSystem {};
+        throw_items.run_now(&self.ecs);
+        let mut encumbrance = EncumbranceSystem {};
+        encumbrance.run_now(&self.ecs);
         self.ecs.maintain();
     }
 }
@@ -89,25 +146,8 @@ impl GameState for State {
         match newrunstate {
             RunState::MainMenu {..} => {}
             _ => {
-                draw_map(&self.ecs, ctx);
-
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let map = self.ecs.fetch::<Map>();
-                    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
-
-                    for (pos, render) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] {
-                            ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph)
-                        }
-                    }
-
-                    gui::draw_ui(&self.ecs, ctx);
-                }
-
+                camera::render_camera(&self.ecs, ctx);
+                gui::draw_ui(&self.ecs, ctx);
             }
         }
 
@@ -119,18 +159,20 @@ impl GameState for State {
             RunState::AwaitingInput => {
                 newrunstate = player_input(self, ctx);
             }
-            RunState::PlayerTurn => {
-                self.run_systems();
-                newrunstate = RunState::MonsterTurn;
-            }
-            RunState::MonsterTurn => {
-                self.run_systems();
-                newrunstate = RunState::AwaitingInput;
+            RunState::Ticking => {
+                // Keep the clock running until it is the player's turn again
+                while newrunstate == RunState::Ticking {
+                    self.run_systems();
+                    damage_system::delete_the_dead(&mut self.ecs);
+                    newrunstate = *self.ecs.fetch::<RunState>();
+                }
             }
             RunState::ItemMenu(op) => {
                 let result = match op {
                     ItemMenuOp::Use => gui::show_inventory(self, ctx),
                     ItemMenuOp::Drop => gui::drop_item_menu(self, ctx),
+                    ItemMenuOp::Remove => gui::remove_item_menu(self, ctx),
+                    ItemMenuOp::Throw => gui::throw_item_menu(self, ctx),
                 };
                 match result {
                     ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
@@ -145,14 +187,25 @@ impl GameState for State {
                                 } else {
                                     let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                                     intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem { item: item_entity, target: None }).expect("Unable to insert intent");
-                                    newrunstate = RunState::PlayerTurn;
+                                    newrunstate = RunState::Ticking;
                                 }
                             }
                             ItemMenuOp::Drop => {
-                                let mut intent = self.ecs.write_storage::<WantsToDropItem>();
-                                intent.insert(*self.ecs.fetch::<Entity>(), WantsToDropItem { item: item_entity }).expect("Unable to insert intent");
-                                newrunstate = RunState::PlayerTurn;
+                                let stack_size = stack_size(&self.ecs, item_entity);
+                                if stack_size > 1 {
+                                    newrunstate = RunState::DropQuantity { item: item_entity, quantity: 1, max: stack_size };
+                                } else {
+                                    let mut intent = self.ecs.write_storage::<WantsToDropItem>();
+                                    intent.insert(*self.ecs.fetch::<Entity>(), WantsToDropItem { item: item_entity, quantity: 1 }).expect("Unable to insert intent");
+                                    newrunstate = RunState::Ticking;
+                                }
                             }
+                            ItemMenuOp::Remove => {
+                                let mut intent = self.ecs.write_storage::<WantsToRemoveItem>();
+                                intent.insert(*self.ecs.fetch::<Entity>(), WantsToRemoveItem { item: item_entity }).expect("Unable to insert intent");
+                                newrunstate = RunState::Ticking;
+                            }
+                            ItemMenuOp::Throw => newrunstate = RunState::ThrowTargeting { item: item_entity },
                         }
                     }
                 }
@@ -165,7 +218,84 @@ impl GameState for State {
                     TargetingResult::SelectedPoint(point) => {
                         let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                         intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem { item, target: Some(point) }).expect("Unable to insert intent");
-                        newrunstate = RunState::PlayerTurn;
+                        newrunstate = RunState::Ticking;
+                    }
+                }
+            }
+            RunState::FireTargeting { range } => {
+                let result = gui::ranged_target(self, ctx, range);
+                match result {
+                    TargetingResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    TargetingResult::NoResponse => {}
+                    TargetingResult::SelectedPoint(target) => {
+                        let mut intent = self.ecs.write_storage::<WantsToShoot>();
+                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToShoot { target }).expect("Unable to insert intent");
+                        newrunstate = RunState::Ticking;
+                    }
+                }
+            }
+            RunState::ThrowTargeting { item } => {
+                let result = gui::ranged_target(self, ctx, THROW_RANGE);
+                match result {
+                    TargetingResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    TargetingResult::NoResponse => {}
+                    TargetingResult::SelectedPoint(target) => {
+                        let mut intent = self.ecs.write_storage::<WantsToThrowItem>();
+                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToThrowItem { item, target }).expect("Unable to insert intent");
+                        newrunstate = RunState::Ticking;
+                    }
+                }
+            }
+            RunState::MagicMapReveal { row, awaiting_input } => {
+                // Reveal a row per frame, so the map sweeps into view
+                let mut map = self.ecs.fetch_mut::<Map>();
+                for x in 0..map.width {
+                    let idx = map.xy_idx(x, row);
+                    map.revealed_tiles[idx] = true;
+                }
+                if row >= map.height - 1 {
+                    newrunstate = if awaiting_input { RunState::AwaitingInput } else { RunState::Ticking };
+                } else {
+                    newrunstate = RunState::MagicMapReveal { row: row + 1, awaiting_input };
+                }
+            }
+            RunState::Shop { vendor, mode } => {
+                let result = gui::shop_menu(self, ctx, vendor, mode);
+                match result {
+                    ShopResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    ShopResult::NoResponse => {}
+                    ShopResult::SwitchMode => {
+                        let mode = if mode == ShopMode::Buy { ShopMode::Sell } else { ShopMode::Buy };
+                        newrunstate = RunState::Shop { vendor, mode };
+                    }
+                    ShopResult::Selected(item) => match mode {
+                        ShopMode::Buy => shop::buy(&mut self.ecs, vendor, item),
+                        ShopMode::Sell => shop::sell(&mut self.ecs, vendor, item),
+                    }
+                }
+            }
+            RunState::DropQuantity { item, quantity, max } => {
+                let result = gui::quantity_menu(ctx, "Drop How Many?", quantity, max);
+                match result {
+                    QuantityResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    QuantityResult::NoResponse => {}
+                    QuantityResult::Changed(quantity) => newrunstate = RunState::DropQuantity { item, quantity, max },
+                    QuantityResult::Selected(quantity) => {
+                        let mut intent = self.ecs.write_storage::<WantsToDropItem>();
+                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToDropItem { item, quantity }).expect("Unable to insert intent");
+                        newrunstate = RunState::Ticking;
+                    }
+                }
+            }
+            RunState::Look { cursor } => {
+                let result = gui::look_mode(self, ctx, cursor);
+                match result {
+                    LookResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    LookResult::NoResponse => {}
+                    LookResult::Moved(point) => newrunstate = RunState::Look { cursor: point },
+                    LookResult::Selected(point) => {
+                        start_travel(&mut self.ecs, point);
+                        newrunstate = RunState::AwaitingInput;
                     }
                 }
             }
@@ -220,7 +350,7 @@ fn main() -> BError {
     };
     register_all_components(&mut gs);
 
-    let map = Map::new_map_rooms_and_corridors();
+    let map = Map::new_map_rooms_and_corridors(MAPWIDTH, MAPHEIGHT);
     let (player_x, player_y) = map.rooms[0].center();
 
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
@@ -233,8 +363,13 @@ fn main() -> BError {
     gs.ecs.insert(RunState::MainMenu { menu_selection: MainMenuSelection::NewGame });
     gs.ecs.insert(gamelog::GameLog { entries: vec!["Welcome to Rusty Roguelike".to_string()] });
     gs.ecs.insert(RandomNumberGenerator::new());
+    let item_names = identification::ItemNames::new(&mut gs.ecs.write_resource::<RandomNumberGenerator>());
+    gs.ecs.insert(item_names);
+    gs.ecs.insert(AutoPilot::default());
+    gs.ecs.insert(PathingMaps::new());
+    gs.ecs.insert(noise::Noises { sources: Vec::new() });
     for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gs.ecs, room);
+        spawner::spawn_room(&mut gs.ecs, &map, room);
     }
     gs.ecs.insert(map);
 
@@ -1,13 +1,12 @@
 use std::cmp::{max, min};
 use bracket_lib::algorithm_traits::{BaseMap, SmallVec};
-use bracket_lib::prelude::{Algorithm2D, BTerm, DistanceAlg, Point, RandomNumberGenerator, to_cp437};
-use bracket_lib::color::RGB;
+use bracket_lib::prelude::{Algorithm2D, DistanceAlg, Point, RandomNumberGenerator};
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 
-pub const MAPWIDTH: usize = 80;
-pub const MAPHEIGHT: usize = 43;
-pub const MAPCOUNT: usize = MAPHEIGHT * MAPWIDTH;
+/// Default dimensions for newly generated levels.
+pub const MAPWIDTH: i32 = 80;
+pub const MAPHEIGHT: i32 = 43;
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
@@ -81,17 +80,28 @@ impl Map {
         }
     }
 
-    pub fn new_map_rooms_and_corridors() -> Map {
-        let mut map = Map {
-            tiles: vec![TileType::Wall; MAPCOUNT],
+    /// Creates a map of the given size that is solid wall throughout.
+    pub fn new(width: i32, height: i32) -> Map {
+        let tile_count = (width * height) as usize;
+        Map {
+            tiles: vec![TileType::Wall; tile_count],
             rooms: Vec::new(),
-            width: MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles: vec![false; MAPCOUNT],
-            visible_tiles: vec![false; MAPCOUNT],
-            blocked: vec![false; MAPCOUNT],
-            tile_content: vec![Vec::new(); MAPCOUNT]
-        };
+            width,
+            height,
+            revealed_tiles: vec![false; tile_count],
+            visible_tiles: vec![false; tile_count],
+            blocked: vec![false; tile_count],
+            tile_content: vec![Vec::new(); tile_count]
+        }
+    }
+
+    /// Rebuilds the tile content index, which isn't saved, to match the map's dimensions.
+    pub fn reset_content_index(&mut self) {
+        self.tile_content = vec![Vec::new(); (self.width * self.height) as usize];
+    }
+
+    pub fn new_map_rooms_and_corridors(width: i32, height: i32) -> Map {
+        let mut map = Map::new(width, height);
 
         const MAX_ROOMS: i32 = 30;
         const MIN_SIZE: i32 = 6;
@@ -193,36 +203,3 @@ impl Rect {
         ((self.x1 + self.x2) / 2, (self.y1 + self.y2) / 2)
     }
 }
-
-pub fn draw_map(ecs: &World, ctx: &mut BTerm) {
-    let map = ecs.fetch::<Map>();
-
-    let mut y = 0;
-    let mut x = 0;
-    for (idx, tile) in map.tiles.iter().enumerate() {
-        // Render a tile depending on the tile type
-        if map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg;
-            match tile {
-                TileType::Floor => {
-                    glyph = to_cp437('.');
-                    fg = RGB::from_f32(0.5, 0.5, 0.5);
-                }
-                TileType::Wall => {
-                    glyph = to_cp437('#');
-                    fg = RGB::from_f32(0.0, 1.0, 0.0);
-                }
-            }
-            if !map.visible_tiles[idx] { fg = fg.to_greyscale() }
-            ctx.set(x, y, fg, RGB::from_f32(0., 0., 0.), glyph);
-        }
-
-        // Move the coordinates
-        x += 1;
-        if x > map.width - 1 {
-            x = 0;
-            y += 1;
-        }
-    }
-}
@@ -1,33 +1,76 @@
+use bracket_lib::prelude::{Point, RandomNumberGenerator};
 use specs::prelude::*;
-use crate::components::{CombatStats, Name, SufferDamage, WantsToMelee};
+use crate::components::{Attributes, CombatStats, DamageType, Dice, EquipmentSlot, Equipped, MeleeWeapon, Name, NaturalAttack, Position, SufferDamage, WantsToMelee, Wearable};
 use crate::gamelog::GameLog;
+use crate::noise::{Noise, Noises};
+
+/// How far away the sounds of a fight can be heard.
+const FIGHT_NOISE_RADIUS: f32 = 8.0;
+
+/// What a creature with neither a weapon nor a natural attack hits with.
+const UNARMED_DAMAGE: Dice = Dice { n_dice: 1, die_type: 2, bonus: 0 };
 
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (Entities<'a>,
                        WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, Noises>,
+                       WriteExpect<'a, RandomNumberGenerator>,
+                       ReadStorage<'a, Position>,
                        WriteStorage<'a, WantsToMelee>,
                        ReadStorage<'a, Name>,
                        ReadStorage<'a, CombatStats>,
-                       WriteStorage<'a, SufferDamage>);
+                       WriteStorage<'a, SufferDamage>,
+                       ReadStorage<'a, Attributes>,
+                       ReadStorage<'a, NaturalAttack>,
+                       ReadStorage<'a, Equipped>,
+                       ReadStorage<'a, MeleeWeapon>,
+                       ReadStorage<'a, Wearable>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut log, mut wants_melee, names, combat_stats, mut inflict_damage) = data;
+        let (entities, mut log, mut noises, mut rng, positions, mut wants_melee, names, combat_stats, mut inflict_damage,
+            attributes, natural_attacks, equipped, melee_weapons, wearables) = data;
 
-        for (_entity, wants_melee, name, stats) in (&entities, &wants_melee, &names, &combat_stats).join() {
+        for (entity, wants_melee, name, stats) in (&entities, &wants_melee, &names, &combat_stats).join() {
             if stats.hp > 0 {
                 let target_stats = combat_stats.get(wants_melee.target).unwrap();
                 if target_stats.hp > 0 {
                     let target_name = names.get(wants_melee.target).unwrap();
+                    if let Some(pos) = positions.get(entity) {
+                        noises.sources.push(Noise { pos: Point::new(pos.x, pos.y), radius: FIGHT_NOISE_RADIUS });
+                    }
+
+                    // A wielded weapon takes the place of the attacker's natural attack
+                    let wielded = (&entities, &equipped, &names).join()
+                        .filter(|(_item, equipped, _name)| equipped.owner == entity && equipped.slot == EquipmentSlot::Melee)
+                        .find_map(|(item, _equipped, item_name)| melee_weapons.get(item).map(|weapon| (item_name.name.clone(), weapon.hit_bonus, weapon.damage, weapon.damage_type)));
+                    let (weapon_name, hit_bonus, damage_dice, damage_type) = wielded.unwrap_or_else(|| {
+                        natural_attacks.get(entity)
+                            .map_or(("fists".to_string(), 0, UNARMED_DAMAGE, DamageType::Physical), |attack| (attack.name.clone(), attack.hit_bonus, attack.damage, attack.damage_type))
+                    });
+                    let might_bonus = attributes.get(entity).map_or(0, |attr| Attributes::bonus(attr.might));
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
+                    let armour_class = armour_class(wants_melee.target, target_stats, &attributes, &equipped, &wearables);
 
-                    if damage == 0 {
-                        log.entries.push(format!("{} is unable to hurt {}", &name.name, &target_name.name));
+                    let natural_roll = rng.roll_dice(1, 20);
+                    let modifiers = hit_bonus + might_bonus;
+                    let to_hit = natural_roll + modifiers;
+
+                    if natural_roll == 1 {
+                        log.entries.push(format!("{} fumbles an attack on {} (natural 1).", &name.name, &target_name.name));
+                    } else if natural_roll == 20 {
+                        // Criticals roll the weapon's dice twice
+                        let damage = i32::max(1, damage_dice.roll(&mut rng) + damage_dice.roll(&mut rng) + might_bonus);
+                        log.entries.push(format!("{} critically hits {} with {} (natural 20).", &name.name, &target_name.name, weapon_name));
+                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage, damage_type);
+                    } else if to_hit >= armour_class {
+                        let damage = i32::max(1, damage_dice.roll(&mut rng) + might_bonus);
+                        log.entries.push(format!("{} hits {} with {} ({}{:+} vs AC {}).", &name.name, &target_name.name, weapon_name, natural_roll, modifiers, armour_class));
+                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage, damage_type);
                     } else {
-                        log.entries.push(format!("{} hits {}, for {} hp.", &name.name, &target_name.name, damage));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                        log.entries.push(format!("{} misses {} ({}{:+} vs AC {}).", &name.name, &target_name.name, natural_roll, modifiers, armour_class));
                     }
                 }
             }
@@ -36,3 +79,15 @@ impl<'a> System<'a> for MeleeCombatSystem {
         wants_melee.clear();
     }
 }
+
+/// What a to-hit roll against `target` has to reach: their base armour class, plus their quickness
+/// bonus and any armour they are wearing.
+pub fn armour_class(target: Entity, stats: &CombatStats, attributes: &ReadStorage<'_, Attributes>, equipped: &ReadStorage<'_, Equipped>,
+                    wearables: &ReadStorage<'_, Wearable>) -> i32 {
+    let worn_armour: i32 = (equipped, wearables).join()
+        .filter(|(equipped, _wearable)| equipped.owner == target)
+        .map(|(_equipped, wearable)| wearable.armour_class)
+        .sum();
+    let quickness_bonus = attributes.get(target).map_or(0, |attr| Attributes::bonus(attr.quickness));
+    stats.armour_class + quickness_bonus + worn_armour
+}
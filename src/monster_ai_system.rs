@@ -1,8 +1,97 @@
 use bracket_lib::prelude::*;
 use specs::prelude::*;
 use crate::components::*;
+use crate::faction::{self, Reaction};
+use crate::gamelog::GameLog;
 use crate::map::Map;
-use crate::RunState;
+use crate::noise::Noises;
+use crate::pathing_map_system::PathingMaps;
+
+/// Monsters flee once they are down to less than 1/FLEE_HP_FRACTION of their maximum HP.
+const FLEE_HP_FRACTION: i32 = 4;
+
+/// How many turns a monster spends searching around where it lost sight of the player.
+const SEARCH_TURNS: i32 = 5;
+
+/// How far from the player's last known position a searching monster will wander.
+const SEARCH_RADIUS: f32 = 4.0;
+
+/// Sleeping monsters wake up when the player comes this close.
+const WAKE_DISTANCE: f32 = 2.5;
+
+/// Monsters reach for a healing potion once they are down to less than 1/HEAL_HP_FRACTION of their
+/// maximum HP.
+const HEAL_HP_FRACTION: i32 = 2;
+
+/// Decides whether monsters taking a turn should use something from their backpack: a healing
+/// potion when hurt, or a ranged item against the player when they can see them. Runs before
+/// `MonsterAI`, which leaves alone any monster that has decided to use an item.
+pub struct MonsterItemAI {}
+
+impl<'a> System<'a> for MonsterItemAI {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, Point>,
+                       Entities<'a>,
+                       ReadStorage<'a, Monster>,
+                       ReadStorage<'a, Position>,
+                       ReadStorage<'a, Viewshed>,
+                       ReadStorage<'a, MyTurn>,
+                       ReadStorage<'a, StatusEffects>,
+                       ReadStorage<'a, Idle>,
+                       ReadStorage<'a, Faction>,
+                       ReadStorage<'a, CombatStats>,
+                       ReadStorage<'a, InBackpack>,
+                       ReadStorage<'a, ProvidesHealing>,
+                       ReadStorage<'a, Ranged>,
+                       ReadStorage<'a, InflictsDamage>,
+                       ReadStorage<'a, InflictsStatus>,
+                       ReadStorage<'a, AreaOfEffect>,
+                       ReadStorage<'a, Charges>,
+                       WriteStorage<'a, WantsToUseItem>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_pos, entities, monster, position, viewshed, turns, status_effects, idles, factions, combat_stats, backpack,
+            healing, ranged, inflicts_damage, inflicts_status, aoe, charges, mut wants_use) = data;
+
+        for (entity, _monster, pos, viewshed, _turn) in (&entities, &monster, &position, &viewshed, &turns).join() {
+            // Confused or helpless monsters are in no state to rummage through their packs
+            if status_effects.get(entity).is_some_and(|statuses| !statuses.can_act() || statuses.has(StatusKind::Confusion)) { continue; }
+            if idles.get(entity).is_some_and(|idle| idle.behaviour == IdleBehaviour::Sleep) { continue; }
+
+            let carried: Vec<Entity> = (&entities, &backpack).join()
+                .filter(|(_item, pack)| pack.owner == entity)
+                .map(|(item, _pack)| item)
+                .collect();
+            if carried.is_empty() { continue; }
+
+            let hurt = combat_stats.get(entity).is_some_and(|stats| stats.hp < stats.max_hp / HEAL_HP_FRACTION);
+            if hurt {
+                if let Some(potion) = carried.iter().find(|item| healing.get(**item).is_some()) {
+                    wants_use.insert(entity, WantsToUseItem { item: *potion, target: None }).expect("Unable to insert intent");
+                    continue;
+                }
+            }
+
+            let hunts_player = factions.get(entity)
+                .is_some_and(|faction| faction::reaction(&faction.name, faction::PLAYER) == Reaction::Attack);
+            if !hunts_player || !viewshed.visible_tiles.contains(&*player_pos) { continue; }
+
+            let distance = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+            let weapon = carried.iter().find(|item| {
+                let harmful = inflicts_damage.get(**item).is_some() || inflicts_status.get(**item).is_some();
+                let in_range = ranged.get(**item).is_some_and(|ranged| distance <= ranged.range as f32);
+                // Don't get caught in our own blast
+                let safe = aoe.get(**item).is_none_or(|area| distance > area.radius as f32);
+                let charged = charges.get(**item).is_none_or(|charges| charges.current > 0);
+                harmful && in_range && safe && charged
+            });
+            if let Some(weapon) = weapon {
+                wants_use.insert(entity, WantsToUseItem { item: *weapon, target: Some(*player_pos) }).expect("Unable to insert intent");
+            }
+        }
+    }
+}
 
 pub struct MonsterAI {}
 
@@ -11,52 +100,229 @@ impl<'a> System<'a> for MonsterAI {
     type SystemData = (WriteExpect<'a, Map>,
                        ReadExpect<'a, Point>,
                        ReadExpect<'a, Entity>,
-                       ReadExpect<'a, RunState>,
+                       WriteExpect<'a, PathingMaps>,
+                       WriteExpect<'a, RandomNumberGenerator>,
+                       WriteExpect<'a, Noises>,
+                       WriteExpect<'a, GameLog>,
                        Entities<'a>,
                        WriteStorage<'a, Viewshed>,
                        ReadStorage<'a, Monster>,
                        WriteStorage<'a, Position>,
                        WriteStorage<'a, WantsToMelee>,
-                       WriteStorage<'a, Confusion>,
+                       ReadStorage<'a, StatusEffects>,
+                       ReadStorage<'a, MyTurn>,
+                       ReadStorage<'a, CombatStats>,
+                       WriteStorage<'a, Pursuit>,
+                       WriteStorage<'a, Idle>,
+                       ReadStorage<'a, Name>,
+                       ReadStorage<'a, Faction>,
+                       ReadStorage<'a, WantsToUseItem>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, player_pos, player_entity, runstate, entities, mut viewshed, monster, mut position, mut wants_to_melee, mut confused) = data;
+        let (mut map, player_pos, player_entity, mut pathing, mut rng, mut noises, mut gamelog, entities, mut viewshed, monster, mut position,
+            mut wants_to_melee, status_effects, turns, combat_stats, mut pursuits, mut idles, names, factions, wants_use) = data;
+
+        // Sleepers listen out whether or not it is their turn
+        for (idle, pos, name) in (&mut idles, &position, &names).join() {
+            if idle.behaviour != IdleBehaviour::Sleep { continue; }
+
+            let here = Point::new(pos.x, pos.y);
+            let player_close = DistanceAlg::Pythagoras.distance2d(here, *player_pos) <= WAKE_DISTANCE;
+            let heard_noise = noises.sources.iter().any(|noise| DistanceAlg::Pythagoras.distance2d(here, noise.pos) <= noise.radius);
+            if player_close || heard_noise {
+                idle.behaviour = IdleBehaviour::Wander;
+                if map.visible_tiles[map.xy_idx(pos.x, pos.y)] {
+                    gamelog.entries.push(format!("The {} wakes up!", name.name));
+                }
+            }
+        }
+        noises.sources.clear();
 
-        if *runstate != RunState::MonsterTurn { return; }
+        // Where everyone who belongs to a faction is, so that monsters can pick on each other
+        let mut creatures: Vec<(Entity, Point, String)> = (&entities, &position, &factions).join()
+            .map(|(entity, pos, faction)| (entity, Point::new(pos.x, pos.y), faction.name.clone()))
+            .collect();
 
-        for (entity, viewshed, _monster, pos) in (&entities, &mut viewshed, &monster, &mut position).join() {
-            let mut can_act = true;
+        let mut given_up = Vec::new();
 
-            let is_confused = confused.get_mut(entity);
-            if let Some(i_am_confused) = is_confused {
-                i_am_confused.turns -= 1;
-                if i_am_confused.turns < 1 {
-                    confused.remove(entity);
+        for (entity, viewshed, _monster, pos, _turn) in (&entities, &mut viewshed, &monster, &mut position, &turns).join() {
+            // Busy using an item this turn
+            if wants_use.get(entity).is_some() { continue; }
+
+            let my_faction = factions.get(entity).map_or("", |faction| faction.name.as_str());
+
+            if let Some(statuses) = status_effects.get(entity) {
+                if !statuses.can_act() { continue; }
+
+                if statuses.has(StatusKind::Confusion) {
+                    // Confused monsters stumble about at random, lashing out at whoever they bump into
+                    if let Some(stumble) = random_neighbour(&map, &mut rng, pos) {
+                        if let Some((victim, _, _)) = creatures.iter().find(|(other, at, _)| *other != entity && *at == stumble) {
+                            wants_to_melee.insert(entity, WantsToMelee { target: *victim }).expect("Unable to insert attack");
+                        } else {
+                            let idx = map.xy_idx(stumble.x, stumble.y);
+                            if !map.blocked[idx] {
+                                move_monster(&mut map, pos, viewshed, idx);
+                            }
+                        }
+                    }
+                    update_creature(&mut creatures, entity, pos);
+                    continue;
                 }
-                can_act = false;
             }
 
-            if can_act {
-                let distance = DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-                if distance < 1.5 {
-                    wants_to_melee.insert(entity, WantsToMelee { target: *player_entity }).expect("Unable to insert attack");
-                } else if viewshed.visible_tiles.contains(&*player_pos) {
-                    let path = a_star_search(
-                        map.xy_idx(pos.x, pos.y) as i32,
-                        map.xy_idx(player_pos.x, player_pos.y) as i32,
-                        &mut *map);
-                    if path.success && path.steps.len() > 1 {
-                        let mut idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = false;
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
-                        idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = true;
-                        viewshed.dirty = true;
+            if idles.get(entity).is_some_and(|idle| idle.behaviour == IdleBehaviour::Sleep) { continue; }
+
+            let here = Point::new(pos.x, pos.y);
+            let my_idx = map.xy_idx(pos.x, pos.y);
+            let can_see_player = viewshed.visible_tiles.contains(&*player_pos);
+            let hunts_player = faction::reaction(my_faction, faction::PLAYER) == Reaction::Attack;
+            let badly_hurt = combat_stats.get(entity).is_some_and(|stats| stats.hp < stats.max_hp / FLEE_HP_FRACTION);
+
+            // Deal with the nearest creature we have feelings about
+            let target = creatures.iter()
+                .filter(|(other, at, _)| *other != entity && viewshed.visible_tiles.contains(at))
+                .map(|(other, at, theirs)| (*other, *at, faction::reaction(my_faction, theirs)))
+                .filter(|(_, _, reaction)| *reaction != Reaction::Ignore)
+                .min_by(|(_, a, _), (_, b, _)| {
+                    let distance_a = DistanceAlg::Pythagoras.distance2d(here, *a);
+                    let distance_b = DistanceAlg::Pythagoras.distance2d(here, *b);
+                    distance_a.partial_cmp(&distance_b).expect("Distances should be comparable")
+                });
+
+            if let Some((target, target_pos, reaction)) = target {
+                let distance = DistanceAlg::Pythagoras.distance2d(here, target_pos);
+                let is_player = target == *player_entity;
+
+                if reaction == Reaction::Flee || badly_hurt {
+                    // Run away, but fight back if cornered
+                    let escape = if is_player {
+                        DijkstraMap::find_lowest_exit(&pathing.flee, my_idx, &*map)
+                            .filter(|idx| pathing.flee.map[*idx] < pathing.flee.map[my_idx])
+                    } else {
+                        step_away(&map, my_idx, target_pos)
+                    };
+                    if let Some(idx) = escape {
+                        move_monster(&mut map, pos, viewshed, idx);
+                    } else if distance < 1.5 {
+                        wants_to_melee.insert(entity, WantsToMelee { target }).expect("Unable to insert attack");
+                    }
+                } else if distance < 1.5 {
+                    wants_to_melee.insert(entity, WantsToMelee { target }).expect("Unable to insert attack");
+                } else if is_player {
+                    if let Some(idx) = DijkstraMap::find_lowest_exit(&pathing.approach, my_idx, &*map) {
+                        move_monster(&mut map, pos, viewshed, idx);
+                    }
+                } else {
+                    let target_idx = map.xy_idx(target_pos.x, target_pos.y);
+                    if let Some(idx) = step_towards(&mut pathing, &map, my_idx, target_idx) {
+                        move_monster(&mut map, pos, viewshed, idx);
                     }
                 }
+            } else if let Some(pursuit) = pursuits.get_mut(entity).filter(|_| !badly_hurt) {
+                // Head for where the player was last seen, then search around there for a while
+                let last_seen_idx = map.xy_idx(pursuit.last_seen.x, pursuit.last_seen.y);
+                let step = if my_idx == last_seen_idx { None } else { step_towards(&mut pathing, &map, my_idx, last_seen_idx) };
+                if let Some(idx) = step {
+                    move_monster(&mut map, pos, viewshed, idx);
+                } else {
+                    if let Some(idx) = random_step(&map, &mut rng, pos) {
+                        let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+                        if DistanceAlg::Pythagoras.distance2d(Point::new(x, y), pursuit.last_seen) <= SEARCH_RADIUS {
+                            move_monster(&mut map, pos, viewshed, idx);
+                        }
+                    }
+                    pursuit.search_turns -= 1;
+                    if pursuit.search_turns < 1 {
+                        given_up.push(entity);
+                    }
+                }
+            } else if let Some(idle) = idles.get_mut(entity) {
+                match &mut idle.behaviour {
+                    IdleBehaviour::Wander => {
+                        // Dawdle now and then, so wanderers don't look frantic
+                        if rng.roll_dice(1, 3) > 1 {
+                            if let Some(idx) = random_step(&map, &mut rng, pos) {
+                                move_monster(&mut map, pos, viewshed, idx);
+                            }
+                        }
+                    }
+                    IdleBehaviour::Patrol { route, next } => {
+                        if !route.is_empty() {
+                            let waypoint = route[*next % route.len()];
+                            let waypoint_idx = map.xy_idx(waypoint.x, waypoint.y);
+                            let step = if my_idx == waypoint_idx { None } else { step_towards(&mut pathing, &map, my_idx, waypoint_idx) };
+                            if let Some(idx) = step {
+                                move_monster(&mut map, pos, viewshed, idx);
+                            } else {
+                                *next = (*next + 1) % route.len();
+                            }
+                        }
+                    }
+                    IdleBehaviour::Sleep => {}
+                }
+            }
+
+            if can_see_player && hunts_player {
+                pursuits.insert(entity, Pursuit { last_seen: *player_pos, search_turns: SEARCH_TURNS }).expect("Unable to insert pursuit");
             }
+            update_creature(&mut creatures, entity, pos);
+        }
+
+        for entity in given_up {
+            pursuits.remove(entity);
         }
     }
-}
\ No newline at end of file
+}
+
+/// Picks a random tile next to `pos`, if the roll lands on one inside the map.
+fn random_neighbour(map: &Map, rng: &mut RandomNumberGenerator, pos: &Position) -> Option<Point> {
+    let x = pos.x + rng.range(-1, 2);
+    let y = pos.y + rng.range(-1, 2);
+    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 { return None; }
+    if x == pos.x && y == pos.y { return None; }
+    Some(Point::new(x, y))
+}
+
+/// Picks a random unblocked tile next to `pos`, if the roll lands on one.
+fn random_step(map: &Map, rng: &mut RandomNumberGenerator, pos: &Position) -> Option<usize> {
+    random_neighbour(map, rng, pos)
+        .map(|point| map.xy_idx(point.x, point.y))
+        .filter(|idx| !map.blocked[*idx])
+}
+
+/// Picks the neighbouring tile that is closest to the tile at `goal`, using the shared pathing map
+/// towards it, if the goal can be reached at all.
+fn step_towards(pathing: &mut PathingMaps, map: &Map, idx: usize, goal: usize) -> Option<usize> {
+    let towards = pathing.towards(map, goal);
+    DijkstraMap::find_lowest_exit(towards, idx, map).filter(|exit| towards.map[*exit] < f32::MAX)
+}
+
+/// Picks the neighbouring tile that gets furthest from `threat`, if any of them is further than
+/// where we are now.
+fn step_away(map: &Map, idx: usize, threat: Point) -> Option<usize> {
+    let distance_from_threat = |idx: usize| {
+        DistanceAlg::Pythagoras.distance2d(Point::new(idx as i32 % map.width, idx as i32 / map.width), threat)
+    };
+    map.get_available_exits(idx).iter()
+        .map(|(exit, _cost)| *exit)
+        .filter(|exit| distance_from_threat(*exit) > distance_from_threat(idx))
+        .max_by(|a, b| distance_from_threat(*a).partial_cmp(&distance_from_threat(*b)).expect("Distances should be comparable"))
+}
+
+/// Keeps the snapshot of creature positions up to date after a monster has moved.
+fn update_creature(creatures: &mut [(Entity, Point, String)], entity: Entity, pos: &Position) {
+    if let Some(creature) = creatures.iter_mut().find(|(other, _, _)| *other == entity) {
+        creature.1 = Point::new(pos.x, pos.y);
+    }
+}
+
+fn move_monster(map: &mut Map, pos: &mut Position, viewshed: &mut Viewshed, idx: usize) {
+    let old_idx = map.xy_idx(pos.x, pos.y);
+    map.blocked[old_idx] = false;
+    pos.x = idx as i32 % map.width;
+    pos.y = idx as i32 / map.width;
+    map.blocked[idx] = true;
+    viewshed.dirty = true;
+}
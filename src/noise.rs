@@ -0,0 +1,12 @@
+use bracket_lib::prelude::Point;
+
+/// A loud event, such as a fight, that can wake sleeping monsters within `radius` tiles.
+pub struct Noise {
+    pub pos: Point,
+    pub radius: f32,
+}
+
+/// Noises made since the monsters last listened for them.
+pub struct Noises {
+    pub sources: Vec<Noise>
+}
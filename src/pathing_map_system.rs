@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+use crate::map::Map;
+
+/// How far from the player the pathing maps are filled in.
+const MAX_DEPTH: f32 = 200.0;
+
+/// Scales the approach map when deriving the flee map. It is negative so that distance from the
+/// player becomes downhill, and a magnitude above 1 makes fleeing monsters prefer to run past the
+/// player towards a more distant refuge over backing into a dead end.
+const FLEE_FACTOR: f32 = -1.2;
+
+/// Dijkstra maps shared by every monster, so that each turn's pathfinding is done once rather than
+/// once per monster.
+pub struct PathingMaps {
+    /// Distance to the player. Rolling downhill approaches them.
+    pub approach: DijkstraMap,
+    /// Rolling downhill on this map leads away from the player.
+    pub flee: DijkstraMap,
+    /// Distance to other tiles monsters are heading for, keyed by map index. Each one is built the
+    /// first time it is asked for and shared with anyone else heading the same way that tick.
+    towards: HashMap<usize, DijkstraMap>,
+    built_for: Option<Point>,
+}
+
+impl PathingMaps {
+    /// Creates empty maps, which are filled in the first time `PathingMapSystem` runs.
+    pub fn new() -> PathingMaps {
+        PathingMaps {
+            approach: DijkstraMap::new_empty(0, 0, MAX_DEPTH),
+            flee: DijkstraMap::new_empty(0, 0, MAX_DEPTH),
+            towards: HashMap::new(),
+            built_for: None,
+        }
+    }
+
+    /// Forces the approach and flee maps to be rebuilt next time, for when the walls have changed
+    /// under them.
+    pub fn invalidate(&mut self) {
+        self.built_for = None;
+    }
+
+    /// The map leading to the tile at `idx`. Rolling downhill approaches it.
+    pub fn towards(&mut self, map: &Map, idx: usize) -> &DijkstraMap {
+        self.towards.entry(idx).or_insert_with(|| DijkstraMap::new(map.width, map.height, &[idx], map, MAX_DEPTH))
+    }
+}
+
+/// Rebuilds the shared pathing maps whenever the player has moved, so at most once per turn. Maps
+/// towards anywhere else only last for the tick they were built in, since whatever monsters are
+/// after is likely to have moved by the next.
+pub struct PathingMapSystem {}
+
+impl<'a> System<'a> for PathingMapSystem {
+    type SystemData = (ReadExpect<'a, Map>,
+                       ReadExpect<'a, Point>,
+                       WriteExpect<'a, PathingMaps>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, player_pos, mut pathing) = data;
+
+        pathing.towards.clear();
+
+        if pathing.built_for == Some(*player_pos) && pathing.approach.map.len() == map.tiles.len() { return; }
+
+        // Creatures move about all turn, so only walls count. That way the tile a monster is
+        // standing on has a value of its own to compare its exits against.
+        let mut terrain = (*map).clone();
+        terrain.populate_blocked();
+
+        let player_idx = terrain.xy_idx(player_pos.x, player_pos.y);
+        pathing.approach = DijkstraMap::new(terrain.width, terrain.height, &[player_idx], &terrain, MAX_DEPTH);
+        pathing.flee = build_flee_map(&pathing.approach, &terrain);
+        pathing.built_for = Some(*player_pos);
+    }
+}
+
+/// Inverts the approach map and lets the values settle, so that every tile slopes down towards the
+/// places furthest from the player rather than just directly away from them.
+fn build_flee_map(approach: &DijkstraMap, map: &Map) -> DijkstraMap {
+    let mut flee = DijkstraMap::new_empty(map.width, map.height, MAX_DEPTH);
+    for (idx, distance) in approach.map.iter().enumerate() {
+        if *distance < f32::MAX {
+            flee.map[idx] = *distance * FLEE_FACTOR;
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in 0..flee.map.len() {
+            if flee.map[idx] == f32::MAX { continue; }
+            for (exit, cost) in map.get_available_exits(idx).iter() {
+                let candidate = flee.map[idx] + *cost;
+                if candidate < flee.map[*exit] {
+                    flee.map[*exit] = candidate;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    flee
+}
@@ -1,49 +1,116 @@
 use bracket_lib::prelude::*;
 use specs::prelude::*;
 use std::cmp::{max, min};
-use crate::{RunState, State};
-use crate::components::{CombatStats, Item, Player, Position, Viewshed, WantsToMelee, WantsToPickupItem};
+use crate::{RunState, ShopMode, State};
+use crate::camera::{screen_to_map, VIEW_HEIGHT, VIEW_WIDTH};
+use crate::components::{Ammunition, CombatStats, EquipmentSlot, Equipped, Faction, InBackpack, Item, Monster, Name, Player, Position, RangedWeapon, StatusEffects, StatusKind, Vendor, Viewshed, WantsToMelee, WantsToPickupItem};
+use crate::faction::{self, Reaction};
 use crate::gamelog::GameLog;
-use crate::ItemMenuOp::{Drop, Use};
-use crate::map::Map;
+use crate::identification::ItemNames;
+use crate::ItemMenuOp::{Drop, Remove, Throw, Use};
+use crate::map::{Map, TileType};
+
+/// A multi-turn action that the player carries out one step per turn instead of waiting for a key.
+pub enum AutoAction {
+    Travel { path: Vec<usize> },
+    Explore,
+    Rest,
+}
+
+/// The player's current multi-turn action, if any.
+#[derive(Default)]
+pub struct AutoPilot {
+    pub action: Option<AutoAction>,
+    /// Set by the damage system whenever the player is hit, even for no damage, so that being
+    /// attacked can interrupt the action.
+    pub hit: bool,
+    /// Items the player has already stopped at, which auto-explore won't head towards again.
+    pub seen_items: Vec<Entity>,
+}
 
 pub fn player_input(gs: &mut State, ctx: &mut BTerm) -> RunState {
+    if !player_can_act(&gs.ecs) {
+        gs.ecs.fetch_mut::<GameLog>().entries.push("You can't move!".to_string());
+        gs.ecs.write_resource::<AutoPilot>().action = None;
+        return RunState::Ticking;
+    }
+
+    if gs.ecs.fetch::<AutoPilot>().action.is_some() {
+        if ctx.key.is_some() || ctx.left_click {
+            // Any input cancels the action and is then handled as usual
+            stop_auto_action(&mut gs.ecs, "You stop.");
+        } else {
+            return continue_auto_action(&mut gs.ecs);
+        }
+    }
+
+    // Click to travel
+    if ctx.left_click {
+        let (x, y) = ctx.mouse_pos();
+        if x < VIEW_WIDTH && y < VIEW_HEIGHT {
+            let destination = screen_to_map(&gs.ecs, Point::new(x, y));
+            start_travel(&mut gs.ecs, destination);
+        }
+        return RunState::AwaitingInput;
+    }
+
     // Player movement
     match ctx.key {
         None => { return RunState::AwaitingInput; } // Nothing happened
         Some(key) => match key {
             VirtualKeyCode::Left |
             VirtualKeyCode::Numpad4 |
-            VirtualKeyCode::H => try_move_player(-1, 0, &mut gs.ecs),
+            VirtualKeyCode::H => return try_move_player(-1, 0, &mut gs.ecs),
 
             VirtualKeyCode::Right |
             VirtualKeyCode::Numpad6 |
-            VirtualKeyCode::L => try_move_player(1, 0, &mut gs.ecs),
+            VirtualKeyCode::L => return try_move_player(1, 0, &mut gs.ecs),
 
             VirtualKeyCode::Up |
             VirtualKeyCode::Numpad8 |
-            VirtualKeyCode::K => try_move_player(0, -1, &mut gs.ecs),
+            VirtualKeyCode::K => return try_move_player(0, -1, &mut gs.ecs),
 
             VirtualKeyCode::Down |
             VirtualKeyCode::Numpad2 |
-            VirtualKeyCode::J => try_move_player(0, 1, &mut gs.ecs),
+            VirtualKeyCode::J => return try_move_player(0, 1, &mut gs.ecs),
 
             // Diagonals
             VirtualKeyCode::Numpad9 |
-            VirtualKeyCode::U => try_move_player(1, -1, &mut gs.ecs),
+            VirtualKeyCode::U => return try_move_player(1, -1, &mut gs.ecs),
 
             VirtualKeyCode::Numpad7 |
-            VirtualKeyCode::Y => try_move_player(-1, -1, &mut gs.ecs),
+            VirtualKeyCode::Y => return try_move_player(-1, -1, &mut gs.ecs),
 
             VirtualKeyCode::Numpad3 |
-            VirtualKeyCode::N => try_move_player(1, 1, &mut gs.ecs),
+            VirtualKeyCode::N => return try_move_player(1, 1, &mut gs.ecs),
 
             VirtualKeyCode::Numpad1 |
-            VirtualKeyCode::B => try_move_player(-1, 1, &mut gs.ecs),
+            VirtualKeyCode::B => return try_move_player(-1, 1, &mut gs.ecs),
 
             VirtualKeyCode::G => get_item(&mut gs.ecs),
             VirtualKeyCode::I => return RunState::ItemMenu(Use),
             VirtualKeyCode::D => return RunState::ItemMenu(Drop),
+            VirtualKeyCode::T => return RunState::ItemMenu(Remove),
+            VirtualKeyCode::V => return RunState::ItemMenu(Throw),
+            VirtualKeyCode::F => return fire_weapon(&mut gs.ecs),
+            VirtualKeyCode::X => return RunState::Look { cursor: *gs.ecs.fetch::<Point>() },
+            VirtualKeyCode::O => {
+                start_auto_action(&mut gs.ecs, AutoAction::Explore);
+                return RunState::AwaitingInput;
+            }
+
+            // Wait a turn, or rest until healed
+            VirtualKeyCode::Numpad5 |
+            VirtualKeyCode::Period |
+            VirtualKeyCode::Space => {}
+            VirtualKeyCode::R => {
+                if player_hp(&gs.ecs) >= player_max_hp(&gs.ecs) {
+                    gs.ecs.fetch_mut::<GameLog>().entries.push("You are already at full health.".to_string());
+                } else {
+                    start_auto_action(&mut gs.ecs, AutoAction::Rest);
+                }
+                return RunState::AwaitingInput;
+            }
 
             // Save and Quit
             VirtualKeyCode::Escape => return RunState::SaveGame,
@@ -51,28 +118,45 @@ pub fn player_input(gs: &mut State, ctx: &mut BTerm) -> RunState {
             _ => { return RunState::AwaitingInput; }
         }
     }
-    RunState::PlayerTurn
+    RunState::Ticking
 }
 
-fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// Moves the player, or attacks whatever is in the way. Bumping into a vendor opens their shop
+/// instead.
+fn try_move_player(mut delta_x: i32, mut delta_y: i32, ecs: &mut World) -> RunState {
+    if player_has_status(ecs, StatusKind::Confusion) {
+        // Confusion sends the player stumbling in a random direction, re-rolling a step that goes
+        // nowhere
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        loop {
+            delta_x = rng.range(-1, 2);
+            delta_y = rng.range(-1, 2);
+            if delta_x != 0 || delta_y != 0 { break; }
+        }
+    }
+
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let vendors = ecs.read_storage::<Vendor>();
     let map = ecs.fetch::<Map>();
 
     let entities = ecs.entities();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
 
     for (entity, _player, pos, viewshed) in (&entities, &mut players, &mut positions, &mut viewsheds).join() {
-        if pos.x + delta_x < 1 || pos.x + delta_x > map.width - 1 || pos.y + delta_y < 1 || pos.y + delta_y > map.height - 1 { return; }
+        if pos.x + delta_x < 1 || pos.x + delta_x > map.width - 1 || pos.y + delta_y < 1 || pos.y + delta_y > map.height - 1 { return RunState::Ticking; }
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
-        for potential_target in map.tile_content[destination_idx].iter() {
+        for potential_target in map.tile_content[destination_idx].iter().filter(|target| **target != entity) {
+            if vendors.get(*potential_target).is_some() {
+                return RunState::Shop { vendor: *potential_target, mode: ShopMode::Buy };
+            }
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee.insert(entity, WantsToMelee { target: *potential_target }).expect("Add target failed");
-                return;
+                return RunState::Ticking;
             }
         }
 
@@ -87,6 +171,36 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             viewshed.dirty = true;
         }
     }
+    RunState::Ticking
+}
+
+/// Starts aiming the player's bow or crossbow, if they have one ready and something to shoot
+/// with it.
+fn fire_weapon(ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let weapon = {
+        let equipped = ecs.read_storage::<Equipped>();
+        let ranged_weapons = ecs.read_storage::<RangedWeapon>();
+        (&equipped, &ranged_weapons).join()
+            .find(|(equipped, _weapon)| equipped.owner == player_entity && equipped.slot == EquipmentSlot::Ranged)
+            .map(|(_equipped, weapon)| weapon.clone())
+    };
+    let Some(weapon) = weapon else {
+        ecs.fetch_mut::<GameLog>().entries.push("You have nothing to shoot with.".to_string());
+        return RunState::AwaitingInput;
+    };
+
+    let has_ammo = {
+        let backpack = ecs.read_storage::<InBackpack>();
+        let ammunition = ecs.read_storage::<Ammunition>();
+        (&backpack, &ammunition).join().any(|(pack, ammunition)| pack.owner == player_entity && ammunition.ammo == weapon.ammo)
+    };
+    if !has_ammo {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You have no {} left.", weapon.ammo.plural()));
+        return RunState::AwaitingInput;
+    }
+
+    RunState::FireTargeting { range: weapon.range }
 }
 
 fn get_item(ecs: &mut World) {
@@ -112,4 +226,204 @@ fn get_item(ecs: &mut World) {
             pickup.insert(*player_entity, WantsToPickupItem { collected_by: *player_entity, item }).expect("Unable to insert want to pickup");
         }
     }
-}
\ No newline at end of file
+}
+
+/// Plans a path from the player to `destination` and starts walking it. Only revealed floor tiles
+/// can be travelled to.
+pub fn start_travel(ecs: &mut World, destination: Point) {
+    let path = {
+        let map = ecs.fetch::<Map>();
+        let player_pos = ecs.fetch::<Point>();
+
+        if destination.x < 0 || destination.x >= map.width || destination.y < 0 || destination.y >= map.height { return; }
+        let destination_idx = map.xy_idx(destination.x, destination.y);
+        if !map.revealed_tiles[destination_idx] || map.tiles[destination_idx] == TileType::Wall { return; }
+        if destination == *player_pos { return; }
+
+        let path = a_star_search(map.xy_idx(player_pos.x, player_pos.y), destination_idx, &*map);
+        if !path.success || path.steps.len() < 2 {
+            ecs.fetch_mut::<GameLog>().entries.push("You can't find a way there.".to_string());
+            return;
+        }
+        path.steps[1..].to_vec()
+    };
+
+    start_auto_action(ecs, AutoAction::Travel { path });
+}
+
+/// Commits the player to `action`, unless there is a monster in view.
+fn start_auto_action(ecs: &mut World, action: AutoAction) {
+    if let Some(monster_name) = visible_monster(ecs) {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("Not with a {} in view!", monster_name));
+        return;
+    }
+
+    let mut autopilot = ecs.write_resource::<AutoPilot>();
+    autopilot.action = Some(action);
+    autopilot.hit = false;
+}
+
+/// Performs the next step of the player's current multi-turn action, or stops it if something
+/// needs the player's attention.
+fn continue_auto_action(ecs: &mut World) -> RunState {
+    if player_has_status(ecs, StatusKind::Confusion) {
+        stop_auto_action(ecs, "You are too confused to keep going.");
+        return RunState::AwaitingInput;
+    }
+
+    if let Some(monster_name) = visible_monster(ecs) {
+        stop_auto_action(ecs, &format!("You spot a {} and stop.", monster_name));
+        return RunState::AwaitingInput;
+    }
+
+    if std::mem::take(&mut ecs.write_resource::<AutoPilot>().hit) {
+        stop_auto_action(ecs, "You are hit and stop.");
+        return RunState::AwaitingInput;
+    }
+
+    if matches!(ecs.fetch::<AutoPilot>().action, Some(AutoAction::Rest)) {
+        return rest_turn(ecs);
+    }
+
+    let exploring = matches!(ecs.fetch::<AutoPilot>().action, Some(AutoAction::Explore));
+    let next_step = if exploring {
+        let step = explore_step(ecs);
+        if step.is_none() {
+            stop_auto_action(ecs, "There is nothing left to explore here.");
+        }
+        step
+    } else if let Some(AutoAction::Travel { path }) = &mut ecs.write_resource::<AutoPilot>().action {
+        if path.is_empty() { None } else { Some(path.remove(0)) }
+    } else {
+        None
+    };
+
+    match next_step {
+        None => {
+            ecs.write_resource::<AutoPilot>().action = None;
+            RunState::AwaitingInput
+        }
+        Some(idx) => auto_step(ecs, idx),
+    }
+}
+
+/// Spends a turn resting, recovering a hit point. Resting ends once the player is fully healed.
+fn rest_turn(ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut combat_stats = ecs.write_storage::<CombatStats>();
+    let Some(stats) = combat_stats.get_mut(player_entity) else { return RunState::AwaitingInput };
+    stats.hp = i32::min(stats.max_hp, stats.hp + 1);
+    if stats.hp == stats.max_hp {
+        ecs.write_resource::<AutoPilot>().action = None;
+        ecs.fetch_mut::<GameLog>().entries.push("You feel rested.".to_string());
+    }
+    RunState::Ticking
+}
+
+/// Picks the next tile to explore, by rolling downhill on a Dijkstra map seeded from the edge of the
+/// revealed area and from any visible items the player hasn't stopped at yet.
+fn explore_step(ecs: &World) -> Option<usize> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = ecs.fetch::<Point>();
+    let autopilot = ecs.fetch::<AutoPilot>();
+    let entities = ecs.entities();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+    let mut starts: Vec<usize> = Vec::new();
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if map.revealed_tiles[idx] { continue; }
+            let borders_revealed_floor = [idx - 1, idx + 1, idx - map.width as usize, idx + map.width as usize].iter()
+                .any(|n| map.revealed_tiles[*n] && map.tiles[*n] != TileType::Wall);
+            if borders_revealed_floor {
+                starts.push(idx);
+            }
+        }
+    }
+    for (entity, _item, pos) in (&entities, &items, &positions).join() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        if map.visible_tiles[idx] && idx != player_idx && !autopilot.seen_items.contains(&entity) {
+            starts.push(idx);
+        }
+    }
+    if starts.is_empty() { return None; }
+
+    let dijkstra = DijkstraMap::new(map.width, map.height, &starts, &*map, (map.width * map.height) as f32);
+    if dijkstra.map[player_idx] == f32::MAX { return None; }
+    DijkstraMap::find_lowest_exit(&dijkstra, player_idx, &*map)
+}
+
+/// Moves the player onto the adjacent tile `idx` as part of a multi-turn action, stopping if the
+/// way is blocked or there is an item there.
+fn auto_step(ecs: &mut World, idx: usize) -> RunState {
+    let (delta_x, delta_y, item_here) = {
+        let map = ecs.fetch::<Map>();
+        if map.blocked[idx] {
+            drop(map);
+            stop_auto_action(ecs, "Your path is blocked.");
+            return RunState::AwaitingInput;
+        }
+        let player_pos = ecs.fetch::<Point>();
+        let items = ecs.read_storage::<Item>();
+        let item_here = map.tile_content[idx].iter().copied().find(|e| items.get(*e).is_some());
+        (idx as i32 % map.width - player_pos.x, idx as i32 / map.width - player_pos.y, item_here)
+    };
+
+    try_move_player(delta_x, delta_y, ecs);
+
+    if let Some(item) = item_here {
+        let item_name = ecs.read_storage::<Name>().get(item).map_or("something".to_string(), |name| ecs.fetch::<ItemNames>().describe(&name.name));
+        ecs.write_resource::<AutoPilot>().seen_items.push(item);
+        stop_auto_action(ecs, &format!("You see a {} here.", item_name));
+    } else {
+        let mut autopilot = ecs.write_resource::<AutoPilot>();
+        if matches!(&autopilot.action, Some(AutoAction::Travel { path }) if path.is_empty()) {
+            autopilot.action = None;
+        }
+    }
+
+    RunState::Ticking
+}
+
+fn stop_auto_action(ecs: &mut World, message: &str) {
+    ecs.write_resource::<AutoPilot>().action = None;
+    ecs.fetch_mut::<GameLog>().entries.push(message.to_string());
+}
+
+/// Returns the name of a hostile monster the player can currently see, if there is one. Creatures
+/// that don't want to attack the player don't count.
+fn visible_monster(ecs: &World) -> Option<String> {
+    let map = ecs.fetch::<Map>();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+    let factions = ecs.read_storage::<Faction>();
+
+    (&monsters, &positions, &names, &factions).join()
+        .filter(|(_monster, _pos, _name, faction)| faction::reaction(&faction.name, faction::PLAYER) == Reaction::Attack)
+        .find(|(_monster, pos, _name, _faction)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+        .map(|(_monster, _pos, name, _faction)| name.name.clone())
+}
+
+fn player_hp(ecs: &World) -> i32 {
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    combat_stats.get(*ecs.fetch::<Entity>()).map_or(0, |stats| stats.hp)
+}
+
+fn player_has_status(ecs: &World, kind: StatusKind) -> bool {
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    status_effects.get(*ecs.fetch::<Entity>()).is_some_and(|statuses| statuses.has(kind))
+}
+
+fn player_can_act(ecs: &World) -> bool {
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    status_effects.get(*ecs.fetch::<Entity>()).is_none_or(|statuses| statuses.can_act())
+}
+
+fn player_max_hp(ecs: &World) -> i32 {
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    combat_stats.get(*ecs.fetch::<Entity>()).map_or(0, |stats| stats.max_hp)
+}
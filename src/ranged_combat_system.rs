@@ -0,0 +1,111 @@
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+use crate::components::{Ammunition, Attributes, CombatStats, DamageType, EquipmentSlot, Equipped, InBackpack, Name, Position, RangedWeapon, SufferDamage, WantsToShoot, Wearable};
+use crate::gamelog::GameLog;
+use crate::map::{Map, TileType};
+use crate::melee_combat_system::armour_class;
+use crate::noise::{Noise, Noises};
+
+/// Chance, out of 2, that a shot arrow or bolt is still usable afterwards.
+const RECOVERY_CHANCE: i32 = 1;
+
+/// How far away the sound of a shot can be heard.
+const SHOT_NOISE_RADIUS: f32 = 6.0;
+
+/// Resolves shots from bows and crossbows. The missile flies along a line towards the target and
+/// stops at the first wall or blocking creature in the way, which may not be the one aimed at.
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, Noises>,
+                       WriteExpect<'a, RandomNumberGenerator>,
+                       ReadExpect<'a, Map>,
+                       WriteStorage<'a, WantsToShoot>,
+                       ReadStorage<'a, Name>,
+                       WriteStorage<'a, Position>,
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, CombatStats>,
+                       ReadStorage<'a, Attributes>,
+                       ReadStorage<'a, Equipped>,
+                       ReadStorage<'a, RangedWeapon>,
+                       ReadStorage<'a, Ammunition>,
+                       ReadStorage<'a, Wearable>,
+                       WriteStorage<'a, SufferDamage>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player_entity, mut log, mut noises, mut rng, map, mut wants_shoot, names, mut positions, mut backpack, combat_stats,
+            attributes, equipped, ranged_weapons, ammunition, wearables, mut inflict_damage) = data;
+
+        for (entity, shot) in (&entities, &wants_shoot).join() {
+            let Some(shooter_pos) = positions.get(entity).copied() else { continue };
+            let Some((weapon_name, weapon)) = (&equipped, &ranged_weapons, &names).join()
+                .find(|(equipped, _weapon, _name)| equipped.owner == entity && equipped.slot == EquipmentSlot::Ranged)
+                .map(|(_equipped, weapon, name)| (name.name.clone(), weapon.clone())) else { continue };
+            let ammo = (&entities, &backpack, &ammunition).join()
+                .find(|(_item, pack, ammunition)| pack.owner == entity && ammunition.ammo == weapon.ammo)
+                .map(|(item, _pack, _ammunition)| item);
+            let Some(ammo) = ammo else {
+                if entity == *player_entity {
+                    log.entries.push(format!("You have no {} left.", weapon.ammo.plural()));
+                }
+                continue;
+            };
+            backpack.remove(ammo);
+            noises.sources.push(Noise { pos: Point::new(shooter_pos.x, shooter_pos.y), radius: SHOT_NOISE_RADIUS });
+
+            // Follow the line of fire until it reaches the target, a wall or something in the way
+            let start = Point::new(shooter_pos.x, shooter_pos.y);
+            let mut landing = start;
+            let mut struck = None;
+            for point in line2d(LineAlg::Bresenham, start, shot.target).into_iter().filter(|point| *point != start) {
+                let idx = map.xy_idx(point.x, point.y);
+                if map.tiles[idx] == TileType::Wall { break; }
+                landing = point;
+                if map.blocked[idx] {
+                    struck = map.tile_content[idx].iter().find(|mob| combat_stats.get(**mob).is_some()).copied();
+                    break;
+                }
+            }
+
+            let name = &names.get(entity).unwrap().name;
+            match struck {
+                None => log.entries.push(format!("{} shoots, hitting nothing.", name)),
+                Some(target) => {
+                    let target_name = &names.get(target).unwrap().name;
+                    let target_stats = combat_stats.get(target).unwrap();
+                    let armour_class = armour_class(target, target_stats, &attributes, &equipped, &wearables);
+
+                    let natural_roll = rng.roll_dice(1, 20);
+                    let modifiers = weapon.hit_bonus + attributes.get(entity).map_or(0, |attr| Attributes::bonus(attr.quickness));
+                    let to_hit = natural_roll + modifiers;
+
+                    if natural_roll == 1 {
+                        log.entries.push(format!("{} fumbles a shot at {} (natural 1).", name, target_name));
+                    } else if natural_roll == 20 {
+                        let damage = i32::max(1, weapon.damage.roll(&mut rng) + weapon.damage.roll(&mut rng));
+                        log.entries.push(format!("{} critically shoots {} with {} (natural 20).", name, target_name, weapon_name));
+                        SufferDamage::new_damage(&mut inflict_damage, target, damage, DamageType::Physical);
+                    } else if to_hit >= armour_class {
+                        let damage = i32::max(1, weapon.damage.roll(&mut rng));
+                        log.entries.push(format!("{} shoots {} with {} ({}{:+} vs AC {}).", name, target_name, weapon_name, natural_roll, modifiers, armour_class));
+                        SufferDamage::new_damage(&mut inflict_damage, target, damage, DamageType::Physical);
+                    } else {
+                        log.entries.push(format!("{} shoots at {} and misses ({}{:+} vs AC {}).", name, target_name, natural_roll, modifiers, armour_class));
+                    }
+                }
+            }
+
+            if rng.roll_dice(1, 2) <= RECOVERY_CHANCE {
+                positions.insert(ammo, Position { x: landing.x, y: landing.y }).expect("Unable to insert position");
+            } else {
+                entities.delete(ammo).expect("Delete failed");
+            }
+        }
+
+        wants_shoot.clear();
+    }
+}
@@ -6,6 +6,7 @@ use bracket_lib::prelude::Point;
 use specs::{Builder, Entity, Join, World, WorldExt};
 use specs::saveload::{MarkedBuilder, SimpleMarker, SerializeComponents, DeserializeComponents, SimpleMarkerAllocator};
 use crate::components::*;
+use crate::identification::ItemNames;
 
 const SAVE_PATH: &str = "./savegame.json.gz";
 
@@ -27,9 +28,10 @@ macro_rules! serialize_individually {
 pub fn save_game(ecs: &mut World) {
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let item_names = ecs.get_mut::<ItemNames>().unwrap().clone();
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper { map: mapcopy, item_names })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -42,8 +44,12 @@ pub fn save_game(ecs: &mut World) {
         let mut serializer = serde_json::Serializer::new(gz);
         serialize_individually!(ecs, serializer, data, Position, Renderable, Player, Viewshed, Monster,
             Name, BlocksTile, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
-            AreaOfEffect, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
-            WantsToDropItem, SerializationHelper
+            AreaOfEffect, StatusEffects, InflictsStatus, Energy, Pursuit, Idle, Faction, Corpse, LootTable, Attributes, NaturalAttack,
+            Equippable, Equipped, MeleeWeapon, Wearable, Resistances, HungerClock, ProvidesFood, Identifies, Cursed, RemovesCurse, Charges, Recharges, Stackable, Weight, Encumbered, Value, Gold, Wallet, Vendor,
+            MagicMapper, TeleportsUser, Blinks, Digs,
+            ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
+            WantsToDropItem, WantsToRemoveItem, WantsToThrowItem, Shatters, RangedWeapon, Ammunition, WantsToShoot,
+            SerializationHelper
         );
     }
 
@@ -56,9 +62,10 @@ pub fn save_game(ecs: &mut World) {
 
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let item_names = ecs.get_mut::<ItemNames>().unwrap().clone();
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper { map: mapcopy, item_names })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -73,8 +80,12 @@ pub fn save_game(ecs: &mut World) {
 
         serialize_individually!(ecs, serializer, data, Position, Renderable, Player, Viewshed, Monster,
             Name, BlocksTile, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
-            AreaOfEffect, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
-            WantsToDropItem, SerializationHelper
+            AreaOfEffect, StatusEffects, InflictsStatus, Energy, Pursuit, Idle, Faction, Corpse, LootTable, Attributes, NaturalAttack,
+            Equippable, Equipped, MeleeWeapon, Wearable, Resistances, HungerClock, ProvidesFood, Identifies, Cursed, RemovesCurse, Charges, Recharges, Stackable, Weight, Encumbered, Value, Gold, Wallet, Vendor,
+            MagicMapper, TeleportsUser, Blinks, Digs,
+            ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
+            WantsToDropItem, WantsToRemoveItem, WantsToThrowItem, Shatters, RangedWeapon, Ammunition, WantsToShoot,
+            SerializationHelper
         );
 
         let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
@@ -133,8 +144,12 @@ pub fn load_game(ecs: &mut World) {
 
         deserialize_individually!(ecs, deserializer, d, Position, Renderable, Player, Viewshed, Monster,
             Name, BlocksTile, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
-            AreaOfEffect, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
-            WantsToDropItem, SerializationHelper
+            AreaOfEffect, StatusEffects, InflictsStatus, Energy, Pursuit, Idle, Faction, Corpse, LootTable, Attributes, NaturalAttack,
+            Equippable, Equipped, MeleeWeapon, Wearable, Resistances, HungerClock, ProvidesFood, Identifies, Cursed, RemovesCurse, Charges, Recharges, Stackable, Weight, Encumbered, Value, Gold, Wallet, Vendor,
+            MagicMapper, TeleportsUser, Blinks, Digs,
+            ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
+            WantsToDropItem, WantsToRemoveItem, WantsToThrowItem, Shatters, RangedWeapon, Ammunition, WantsToShoot,
+            SerializationHelper
         );
     }
 
@@ -147,7 +162,8 @@ pub fn load_game(ecs: &mut World) {
         for (e, h) in (&entities, &helper).join() {
             let mut worldmap = ecs.write_resource::<super::map::Map>();
             *worldmap = h.map.clone();
-            worldmap.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
+            worldmap.reset_content_index();
+            *ecs.write_resource::<ItemNames>() = h.item_names.clone();
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
@@ -183,8 +199,12 @@ pub fn load_game(ecs: &mut World) {
 
         deserialize_individually!(ecs, deserializer, d, Position, Renderable, Player, Viewshed, Monster,
             Name, BlocksTile, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage,
-            AreaOfEffect, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
-            WantsToDropItem, SerializationHelper
+            AreaOfEffect, StatusEffects, InflictsStatus, Energy, Pursuit, Idle, Faction, Corpse, LootTable, Attributes, NaturalAttack,
+            Equippable, Equipped, MeleeWeapon, Wearable, Resistances, HungerClock, ProvidesFood, Identifies, Cursed, RemovesCurse, Charges, Recharges, Stackable, Weight, Encumbered, Value, Gold, Wallet, Vendor,
+            MagicMapper, TeleportsUser, Blinks, Digs,
+            ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem,
+            WantsToDropItem, WantsToRemoveItem, WantsToThrowItem, Shatters, RangedWeapon, Ammunition, WantsToShoot,
+            SerializationHelper
         );
     }
 
@@ -197,7 +217,8 @@ pub fn load_game(ecs: &mut World) {
         for (e, h) in (&entities, &helper).join() {
             let mut worldmap = ecs.write_resource::<super::map::Map>();
             *worldmap = h.map.clone();
-            worldmap.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
+            worldmap.reset_content_index();
+            *ecs.write_resource::<ItemNames>() = h.item_names.clone();
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
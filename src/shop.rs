@@ -0,0 +1,75 @@
+use specs::prelude::*;
+use crate::components::{Attributes, Cursed, Equipped, InBackpack, Name, Value, Wallet, Weight};
+use crate::encumbrance_system::{carried_weight, MAX_LOAD_MULTIPLIER};
+use crate::gamelog::GameLog;
+use crate::identification::ItemNames;
+
+/// What a vendor asks for an item worth `value`.
+pub fn buy_price(value: &Value) -> i32 {
+    value.gold
+}
+
+/// What a vendor will pay for an item worth `value`: half, but never nothing.
+pub fn sell_price(value: &Value) -> i32 {
+    i32::max(1, value.gold / 2)
+}
+
+/// Buys `item` from `vendor`'s stock, if the player can afford it and carry it.
+pub fn buy(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let price = buy_price(ecs.read_storage::<Value>().get(item).unwrap());
+    let item_name = ecs.fetch::<ItemNames>().describe(&ecs.read_storage::<Name>().get(item).unwrap().name);
+
+    // The same limit as picking things up off the floor
+    let capacity = ecs.read_storage::<Attributes>().get(player_entity).map(|attr| Attributes::carry_capacity(attr.might));
+    if let Some(capacity) = capacity {
+        let item_weight = ecs.read_storage::<Weight>().get(item).map_or(0.0, |weight| weight.lbs);
+        if carried_weight(ecs, player_entity) + item_weight > capacity * MAX_LOAD_MULTIPLIER {
+            ecs.fetch_mut::<GameLog>().entries.push(format!("The {} is too heavy to carry with everything else.", item_name));
+            return;
+        }
+    }
+
+    if !pay(ecs, player_entity, vendor, price) {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You can't afford the {}.", item_name));
+        return;
+    }
+    ecs.write_storage::<InBackpack>().insert(item, InBackpack { owner: player_entity }).expect("Unable to insert backpack entry");
+    ecs.fetch_mut::<GameLog>().entries.push(format!("You buy the {} for {} gold.", item_name, price));
+}
+
+/// Sells `item` from the player's backpack to `vendor`, if they have the money for it. Cursed gear
+/// being worn won't leave the player's hands, any more than it can be dropped.
+pub fn sell(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let price = sell_price(ecs.read_storage::<Value>().get(item).unwrap());
+    let real_name = ecs.read_storage::<Name>().get(item).unwrap().name.clone();
+    let item_name = ecs.fetch::<ItemNames>().describe(&real_name);
+
+    if ecs.read_storage::<Cursed>().get(item).is_some() && ecs.read_storage::<Equipped>().get(item).is_some() {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("You can't let go of the {}; it's cursed!", item_name));
+        ecs.fetch_mut::<ItemNames>().identify(&real_name);
+        return;
+    }
+
+    if !pay(ecs, vendor, player_entity, price) {
+        ecs.fetch_mut::<GameLog>().entries.push(format!("The shopkeeper can't afford the {}.", item_name));
+        return;
+    }
+    ecs.write_storage::<InBackpack>().insert(item, InBackpack { owner: vendor }).expect("Unable to insert backpack entry");
+    ecs.fetch_mut::<GameLog>().entries.push(format!("You sell the {} for {} gold.", item_name, price));
+}
+
+/// Moves `amount` gold from one wallet to another. Returns false, moving nothing, if the payer
+/// doesn't have enough.
+fn pay(ecs: &mut World, payer: Entity, payee: Entity, amount: i32) -> bool {
+    let mut wallets = ecs.write_storage::<Wallet>();
+    match wallets.get_mut(payer) {
+        Some(wallet) if wallet.gold >= amount => wallet.gold -= amount,
+        _ => return false,
+    }
+    if let Some(wallet) = wallets.get_mut(payee) {
+        wallet.gold += amount;
+    }
+    true
+}
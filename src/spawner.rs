@@ -1,17 +1,28 @@
-use bracket_lib::color::{BLACK, CYAN, MAGENTA, ORANGE, PINK, RED, RGB, YELLOW};
-use bracket_lib::prelude::{FontCharType, to_cp437};
+use bracket_lib::color::{BLACK, BLUE, BROWN1, CYAN, DARK_RED, GREEN, MAGENTA, ORANGE, PINK, RED, RGB, WHITE, YELLOW};
+use bracket_lib::prelude::{console, FontCharType, Point, to_cp437};
 use bracket_lib::random::RandomNumberGenerator;
 use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
-use crate::components::{AreaOfEffect, BlocksTile, CombatStats, Confusion, Consumable, InflictsDamage, Item, Monster, Name, Player, Position, ProvidesHealing, Ranged, Renderable, SerializeMe, Viewshed};
-use crate::map::{MAPWIDTH, Rect};
+use crate::components::{AmmoType, Ammunition, AreaOfEffect, Attributes, Blinks, BlocksTile, Charges, CombatStats, Consumable, Corpse, Cursed, DamageResponse, DamageType, Dice, Digs, Energy, EquipmentSlot, Equippable, Equipped, Faction, Gold, HungerClock, HungerState, Idle, IdleBehaviour, Identifies, InBackpack, InflictsDamage, InflictsStatus, Item, LootDrop, LootTable, MagicMapper, MeleeWeapon, Monster, Name, NaturalAttack, Player, Position, ProvidesFood, ProvidesHealing, Ranged, RangedWeapon, Recharges, RemovesCurse, Renderable, Resistances, SerializeMe, Shatters, Stackable, StatusEffect, StatusKind, TeleportsUser, Value, Vendor, Viewshed, Wallet, Wearable, Weight};
+use crate::faction;
+use crate::hunger_system::WELL_FED_TURNS;
+use crate::initiative_system::NORMAL_SPEED;
+use crate::map::{Map, Rect};
 
 const MAX_MONSTERS: i32 = 4;
 const MAX_ITEMS: i32 = 2;
 
+/// One room in this many is a shop.
+const SHOP_CHANCE: i32 = 8;
+
+/// What shopkeepers might have for sale.
+const SHOP_STOCK: &[&str] = &["Health Potion", "Ration", "Identify Scroll", "Remove Curse Scroll", "Recharging Scroll",
+    "Dagger", "Longsword", "Leather Armour", "Wand of Magic Missile", "Shortbow", "Crossbow"];
+const SHOP_STOCK_SIZE: i32 = 5;
+
 /// Spawns the player and returns their identity object.
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
-    ecs
+    let player = ecs
         .create_entity()
         .with(Position { x: player_x, y: player_y })
         .with(Renderable {
@@ -23,34 +34,161 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
         .with(Player {})
         .with(Viewshed { visible_tiles: Vec::new(), range: 8, dirty: true })
         .with(Name { name: "Player".to_string() })
-        .with(CombatStats { max_hp: 30, hp: 30, defense: 2, power: 5 })
+        .with(CombatStats { max_hp: 30, hp: 30, armour_class: 10 })
+        .with(Attributes { might: 14, quickness: 12 })
+        .with(NaturalAttack { name: "fists".to_string(), hit_bonus: 0, damage: Dice { n_dice: 1, die_type: 3, bonus: 0 }, damage_type: DamageType::Physical })
+        .with(Energy { speed: NORMAL_SPEED, current: 0 })
+        .with(Faction { name: faction::PLAYER.to_string() })
+        .with(Wallet { gold: 0 })
+        .with(HungerClock { state: HungerState::WellFed, duration: WELL_FED_TURNS })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build()
+        .build();
+
+    // Nobody goes into the dungeon empty-handed
+    let dagger = dagger(ecs, player_x, player_y);
+    equip(ecs, player, dagger, EquipmentSlot::Melee);
+
+    player
 }
 
 /// Spawns a random monster at a given location
-pub fn random_monster(ecs: &mut World, x: i32, y: i32) {
+pub fn random_monster(ecs: &mut World, map: &Map, x: i32, y: i32) {
     let roll: i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 2);
+        roll = rng.roll_dice(1, 5);
     }
     match roll {
-        1 => { orc(ecs, x, y) }
-        _ => { goblin(ecs, x, y) }
+        1 | 2 => { orc(ecs, map, x, y) }
+        3 | 4 => { goblin(ecs, x, y) }
+        _ => { rat(ecs, x, y) }
+    }
+}
+
+// Orcs either doze off or walk a beat between their room and a few others
+fn orc(ecs: &mut World, map: &Map, x: i32, y: i32) {
+    let behaviour = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        if rng.roll_dice(1, 2) == 1 || map.rooms.is_empty() {
+            IdleBehaviour::Sleep
+        } else {
+            let mut route = vec![Point::new(x, y)];
+            for _i in 0..rng.roll_dice(1, 3) {
+                let (room_x, room_y) = map.rooms[rng.range(0, map.rooms.len())].center();
+                route.push(Point::new(room_x, room_y));
+            }
+            IdleBehaviour::Patrol { route, next: 0 }
+        }
+    };
+    let orc = monster(ecs, x, y, to_cp437('o'), RGB::named(RED), "Orc")
+        .with(CombatStats { max_hp: 16, hp: 16, armour_class: 11 })
+        .with(Attributes { might: 13, quickness: 9 })
+        .with(NaturalAttack { name: "club".to_string(), hit_bonus: 0, damage: Dice { n_dice: 1, die_type: 6, bonus: 0 }, damage_type: DamageType::Physical })
+        .with(Energy { speed: NORMAL_SPEED, current: 0 })
+        .with(Idle { behaviour })
+        .with(Faction { name: faction::ORCS.to_string() })
+        .with(LootTable { drops: vec![
+            LootDrop { item: "Health Potion".to_string(), chance: 20 },
+            LootDrop { item: "Fireball Scroll".to_string(), chance: 5 },
+        ] })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    // Some orcs keep a potion handy for when a fight goes badly
+    let roll = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 3);
+    if roll == 1 {
+        let potion = health_potion(ecs, x, y);
+        carry(ecs, orc, potion);
+    }
+}
+
+// Goblins are quick on their feet, and sometimes get two moves to the player's one
+fn goblin(ecs: &mut World, x: i32, y: i32) {
+    let goblin = monster(ecs, x, y, to_cp437('g'), RGB::named(RED), "Goblin")
+        .with(CombatStats { max_hp: 12, hp: 12, armour_class: 10 })
+        .with(Attributes { might: 9, quickness: 14 })
+        .with(NaturalAttack { name: "claws".to_string(), hit_bonus: 1, damage: Dice { n_dice: 1, die_type: 4, bonus: 0 }, damage_type: DamageType::Physical })
+        .with(Energy { speed: NORMAL_SPEED * 3 / 2, current: 0 })
+        .with(Idle { behaviour: IdleBehaviour::Wander })
+        .with(Faction { name: faction::GOBLINS.to_string() })
+        .with(Resistances { responses: vec![(DamageType::Fire, DamageResponse::Vulnerable)] })
+        .with(LootTable { drops: vec![
+            LootDrop { item: "Confusion Scroll".to_string(), chance: 10 },
+            LootDrop { item: "Arrow".to_string(), chance: 30 },
+        ] })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    // Goblins like to pester from a distance, when they've got the means
+    let roll = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 3);
+    if roll == 1 {
+        let scroll = magic_missile_scroll(ecs, x, y);
+        carry(ecs, goblin, scroll);
+    }
+}
+
+// Rats are harmless scavengers that scurry away from anything bigger than them
+fn rat(ecs: &mut World, x: i32, y: i32) {
+    monster(ecs, x, y, to_cp437('r'), RGB::named(BROWN1), "Rat")
+        .with(CombatStats { max_hp: 4, hp: 4, armour_class: 10 })
+        .with(Attributes { might: 6, quickness: 14 })
+        .with(NaturalAttack { name: "teeth".to_string(), hit_bonus: 0, damage: Dice { n_dice: 1, die_type: 2, bonus: 0 }, damage_type: DamageType::Poison })
+        .with(Energy { speed: NORMAL_SPEED, current: 0 })
+        .with(Idle { behaviour: IdleBehaviour::Wander })
+        .with(Faction { name: faction::VERMIN.to_string() })
+        .with(Resistances { responses: vec![(DamageType::Poison, DamageResponse::Immune)] })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+/// Shopkeepers stand in the middle of their shop and never go anywhere, which is why they have no
+/// `Monster` or `Energy`. Nobody has any quarrel with them.
+fn shopkeeper(ecs: &mut World, x: i32, y: i32) {
+    let shopkeeper = ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('@'),
+            fg: RGB::named(GREEN),
+            bg: RGB::named(BLACK),
+            render_order: 1,
+        })
+        .with(Name { name: "Shopkeeper".to_string() })
+        .with(BlocksTile {})
+        .with(CombatStats { max_hp: 30, hp: 30, armour_class: 12 })
+        .with(Faction { name: faction::SHOPKEEPERS.to_string() })
+        .with(Vendor {})
+        .with(Wallet { gold: 200 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    for _i in 0..SHOP_STOCK_SIZE {
+        let roll = ecs.write_resource::<RandomNumberGenerator>().range(0, SHOP_STOCK.len());
+        if let Some(item) = named_item(ecs, SHOP_STOCK[roll], x, y) {
+            carry(ecs, shopkeeper, item);
+        }
     }
 }
 
-fn orc(ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, to_cp437('o'), "Orc"); }
+/// Moves a freshly spawned item off the floor and into `owner`'s backpack.
+fn carry(ecs: &mut World, owner: Entity, item: Entity) {
+    ecs.write_storage::<Position>().remove(item);
+    ecs.write_storage::<InBackpack>().insert(item, InBackpack { owner }).expect("Unable to insert backpack entry");
+}
 
-fn goblin(ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, to_cp437('g'), "Goblin"); }
+/// Moves a freshly spawned item off the floor and into `owner`'s hands.
+fn equip(ecs: &mut World, owner: Entity, item: Entity, slot: EquipmentSlot) {
+    ecs.write_storage::<Position>().remove(item);
+    ecs.write_storage::<Equipped>().insert(item, Equipped { owner, slot }).expect("Unable to insert equipped entry");
+}
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: FontCharType, name: S) {
+/// Starts building a monster with the components every monster shares, leaving its stats and
+/// temperament to the caller.
+fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: FontCharType, fg: RGB, name: S) -> EntityBuilder<'_> {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
             glyph,
-            fg: RGB::named(RED),
+            fg,
             bg: RGB::named(BLACK),
             render_order: 1,
         })
@@ -58,28 +196,35 @@ fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: FontCharType, na
         .with(Monster {})
         .with(Name { name: name.to_string() })
         .with(BlocksTile {})
-        .with(CombatStats { max_hp: 16, hp: 16, defense: 1, power: 4 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
 }
 
-/// Fills a room with stuff!
-pub fn spawn_room(ecs: &mut World, room: &Rect) {
+/// Fills a room with stuff! Now and then a room is a shop instead, with a shopkeeper in the middle
+/// and no monsters.
+pub fn spawn_room(ecs: &mut World, map: &Map, room: &Rect) {
     let mut monster_spawn_points: Vec<usize> = Vec::new();
     let mut item_spawn_points: Vec<usize> = Vec::new();
+    let mut gold_spawn: Option<(usize, i32)> = None;
+    let is_shop: bool;
 
     // Scope to keep the borrow checker happy
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_monsters = rng.roll_dice(1, MAX_MONSTERS + 2) - 3;
+        is_shop = rng.roll_dice(1, SHOP_CHANCE) == 1;
+        let num_monsters = if is_shop { 0 } else { rng.roll_dice(1, MAX_MONSTERS + 2) - 3 };
         let num_items = rng.roll_dice(1, MAX_ITEMS + 2) - 3;
 
+        if rng.roll_dice(1, 2) == 1 {
+            let x = room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1));
+            let y = room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1));
+            gold_spawn = Some((map.xy_idx(x, y), rng.roll_dice(3, 10)));
+        }
+
         for _i in 0..num_monsters {
             let mut added = false;
             while !added {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
+                let x = room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1));
+                let y = room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1));
+                let idx = map.xy_idx(x, y);
                 if !monster_spawn_points.contains(&idx) {
                     monster_spawn_points.push(idx);
                     added = true;
@@ -90,9 +235,9 @@ pub fn spawn_room(ecs: &mut World, room: &Rect) {
         for _i in 0..num_items {
             let mut added = false;
             while !added {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
+                let x = room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1));
+                let y = room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1));
+                let idx = map.xy_idx(x, y);
                 if !item_spawn_points.contains(&idx) {
                     item_spawn_points.push(idx);
                     added = true;
@@ -101,36 +246,149 @@ pub fn spawn_room(ecs: &mut World, room: &Rect) {
         }
     }
 
+    if is_shop {
+        let (x, y) = room.center();
+        shopkeeper(ecs, x, y);
+    }
+
     // Actually spawn the monsters
     for idx in monster_spawn_points.iter() {
-        let x = *idx % MAPWIDTH;
-        let y = *idx / MAPWIDTH;
-        random_monster(ecs, x as i32, y as i32);
+        let x = *idx % map.width as usize;
+        let y = *idx / map.width as usize;
+        random_monster(ecs, map, x as i32, y as i32);
     }
 
     // Actually spawn the items
     for idx in item_spawn_points.iter() {
-        let x = *idx % MAPWIDTH;
-        let y = *idx / MAPWIDTH;
+        let x = *idx % map.width as usize;
+        let y = *idx / map.width as usize;
         random_item(ecs, x as i32, y as i32);
     }
+
+    if let Some((idx, amount)) = gold_spawn {
+        let x = idx % map.width as usize;
+        let y = idx / map.width as usize;
+        gold_pile(ecs, x as i32, y as i32, amount);
+    }
+}
+
+/// Spawns an item by name, as used by loot tables and shop stock. Returns None if there is no such
+/// item, which means a misspelt name somewhere, so that gets logged to the console.
+pub fn named_item(ecs: &mut World, name: &str, x: i32, y: i32) -> Option<Entity> {
+    match name {
+        "Health Potion" => Some(health_potion(ecs, x, y)),
+        "Confusion Potion" => Some(confusion_potion(ecs, x, y)),
+        "Fireball Scroll" => Some(fireball_scroll(ecs, x, y)),
+        "Magic Missile Scroll" => Some(magic_missile_scroll(ecs, x, y)),
+        "Confusion Scroll" => Some(confusion_scroll(ecs, x, y)),
+        "Dagger" => Some(dagger(ecs, x, y)),
+        "Longsword" => Some(longsword(ecs, x, y)),
+        "Leather Armour" => Some(leather_armour(ecs, x, y)),
+        "Ration" => Some(ration(ecs, x, y)),
+        "Identify Scroll" => Some(identify_scroll(ecs, x, y)),
+        "Remove Curse Scroll" => Some(remove_curse_scroll(ecs, x, y)),
+        "Recharging Scroll" => Some(recharging_scroll(ecs, x, y)),
+        "Wand of Magic Missile" => Some(wand_of_magic_missile(ecs, x, y)),
+        "Wand of Fire" => Some(wand_of_fire(ecs, x, y)),
+        "Wand of Confusion" => Some(wand_of_confusion(ecs, x, y)),
+        "Wand of Digging" => Some(wand_of_digging(ecs, x, y)),
+        "Shortbow" => Some(shortbow(ecs, x, y)),
+        "Crossbow" => Some(crossbow(ecs, x, y)),
+        "Arrow" => Some(arrow(ecs, x, y)),
+        "Crossbow Bolt" => Some(crossbow_bolt(ecs, x, y)),
+        "Magic Mapping Scroll" => Some(magic_mapping_scroll(ecs, x, y)),
+        "Teleport Scroll" => Some(teleport_scroll(ecs, x, y)),
+        "Blink Scroll" => Some(blink_scroll(ecs, x, y)),
+        "Detect Monsters Scroll" => Some(detect_monsters_scroll(ecs, x, y)),
+        "Poison Potion" => Some(poison_potion(ecs, x, y)),
+        "Regeneration Potion" => Some(regeneration_potion(ecs, x, y)),
+        "Haste Potion" => Some(haste_potion(ecs, x, y)),
+        "Wand of Slowness" => Some(wand_of_slowness(ecs, x, y)),
+        "Wand of Paralysis" => Some(wand_of_paralysis(ecs, x, y)),
+        _ => {
+            console::log(format!("No such item as \"{}\"", name));
+            None
+        }
+    }
+}
+
+/// Leaves the body of a creature called `name` lying at the given location, to be eaten in a pinch.
+pub fn corpse(ecs: &mut World, x: i32, y: i32, name: &str) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('%'),
+            fg: RGB::named(DARK_RED),
+            bg: RGB::named(BLACK),
+            render_order: 3,
+        })
+        .with(Name { name: format!("{} corpse", name) })
+        .with(Corpse {})
+        .with(Item {})
+        .with(Weight { lbs: 20.0 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(ProvidesFood {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
 }
 
 fn random_item(ecs: &mut World, x: i32, y: i32) {
     let roll: i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 3);
+        roll = rng.roll_dice(1, 29);
     }
     match roll {
-        1 => { health_potion(ecs, x, y) }
-        2 => { fireball_scroll(ecs, x, y) }
-        3 => { confusion_scroll(ecs, x, y) }
-        _ => { magic_missile_scroll(ecs, x, y) }
+        1 => { health_potion(ecs, x, y); }
+        2 => { fireball_scroll(ecs, x, y); }
+        3 => { confusion_scroll(ecs, x, y); }
+        4 => { longsword(ecs, x, y); }
+        5 => { leather_armour(ecs, x, y); }
+        6 => { ration(ecs, x, y); }
+        7 => { identify_scroll(ecs, x, y); }
+        8 => { remove_curse_scroll(ecs, x, y); }
+        9 => { cursed_longsword(ecs, x, y); }
+        10 => { cursed_leather_armour(ecs, x, y); }
+        11 => { wand_of_magic_missile(ecs, x, y); }
+        12 => { wand_of_fire(ecs, x, y); }
+        13 => { wand_of_confusion(ecs, x, y); }
+        14 => { recharging_scroll(ecs, x, y); }
+        15 => { confusion_potion(ecs, x, y); }
+        16 => { magic_mapping_scroll(ecs, x, y); }
+        17 => { teleport_scroll(ecs, x, y); }
+        18 => { blink_scroll(ecs, x, y); }
+        19 => { detect_monsters_scroll(ecs, x, y); }
+        20 => { wand_of_digging(ecs, x, y); }
+        21 => { shortbow(ecs, x, y); }
+        22 => { crossbow(ecs, x, y); }
+        23 => { ammunition_bundle(ecs, x, y); }
+        24 => { poison_potion(ecs, x, y); }
+        25 => { regeneration_potion(ecs, x, y); }
+        26 => { haste_potion(ecs, x, y); }
+        27 => { wand_of_slowness(ecs, x, y); }
+        28 => { wand_of_paralysis(ecs, x, y); }
+        _ => { magic_missile_scroll(ecs, x, y); }
     }
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
+pub fn gold_pile(ecs: &mut World, x: i32, y: i32, amount: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('$'),
+            fg: RGB::named(YELLOW),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "pile of gold".to_string() })
+        .with(Item {})
+        .with(Gold { amount })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+fn health_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -141,13 +399,101 @@ fn health_potion(ecs: &mut World, x: i32, y: i32) {
         })
         .with(Name { name: "Health Potion".to_string() })
         .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 30 })
         .with(Consumable {})
+        .with(Stackable {})
         .with(ProvidesHealing { heal_amount: 8 })
+        .with(Shatters {})
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+fn confusion_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('¡'),
+            fg: RGB::named(PINK),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Confusion Potion".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 25 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Confusion, turns: 4 } })
+        .with(Shatters {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
-fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
+fn poison_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('¡'),
+            fg: RGB::named(GREEN),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Poison Potion".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 20 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Poison, turns: 6 } })
+        .with(Shatters {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn regeneration_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('¡'),
+            fg: RGB::named(CYAN),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Regeneration Potion".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 40 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Regeneration, turns: 10 } })
+        .with(Shatters {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn haste_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('¡'),
+            fg: RGB::named(ORANGE),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Haste Potion".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 40 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Haste, turns: 8 } })
+        .with(Shatters {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn fireball_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -158,15 +504,18 @@ fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
         })
         .with(Name { name: "Fireball Scroll".to_string() })
         .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 60 })
         .with(Consumable {})
+        .with(Stackable {})
         .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 20 })
+        .with(InflictsDamage { damage: 20, damage_type: DamageType::Fire })
         .with(AreaOfEffect { radius: 3 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
+fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -177,14 +526,17 @@ fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
         })
         .with(Name { name: "Magic Missile Scroll".to_string() })
         .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 30 })
         .with(Consumable {})
+        .with(Stackable {})
         .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
+        .with(InflictsDamage { damage: 8, damage_type: DamageType::Magic })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
-pub fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
+pub fn confusion_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -195,9 +547,481 @@ pub fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
         })
         .with(Name { name: "Confusion Scroll".to_string() })
         .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 30 })
         .with(Consumable {})
+        .with(Stackable {})
         .with(Ranged { range: 6 })
-        .with(Confusion { turns: 4 })
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Confusion, turns: 4 } })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+fn dagger(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(CYAN),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Dagger".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 1.0 })
+        .with(Value { gold: 10 })
+        .with(Equippable { slot: EquipmentSlot::Melee })
+        .with(MeleeWeapon { hit_bonus: 1, damage: Dice { n_dice: 1, die_type: 4, bonus: 0 }, damage_type: DamageType::Physical })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn longsword(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(YELLOW),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Longsword".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 3.0 })
+        .with(Value { gold: 40 })
+        .with(Equippable { slot: EquipmentSlot::Melee })
+        .with(MeleeWeapon { hit_bonus: 0, damage: Dice { n_dice: 1, die_type: 8, bonus: 0 }, damage_type: DamageType::Physical })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn shortbow(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('}'),
+            fg: RGB::named(BROWN1),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Shortbow".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 2.0 })
+        .with(Value { gold: 30 })
+        .with(Equippable { slot: EquipmentSlot::Ranged })
+        .with(RangedWeapon { range: 8, hit_bonus: 0, damage: Dice { n_dice: 1, die_type: 6, bonus: 0 }, ammo: AmmoType::Arrow })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn crossbow(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('}'),
+            fg: RGB::named(WHITE),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Crossbow".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 6.0 })
+        .with(Value { gold: 50 })
+        .with(Equippable { slot: EquipmentSlot::Ranged })
+        .with(RangedWeapon { range: 10, hit_bonus: 1, damage: Dice { n_dice: 1, die_type: 8, bonus: 1 }, ammo: AmmoType::Bolt })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn arrow(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('|'),
+            fg: RGB::named(BROWN1),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Arrow".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 1 })
+        .with(Stackable {})
+        .with(Ammunition { ammo: AmmoType::Arrow })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn crossbow_bolt(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('|'),
+            fg: RGB::named(WHITE),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Crossbow Bolt".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 2 })
+        .with(Stackable {})
+        .with(Ammunition { ammo: AmmoType::Bolt })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// Ammunition turns up in handfuls, all on the one tile.
+fn ammunition_bundle(ecs: &mut World, x: i32, y: i32) {
+    let (count, arrows) = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        (rng.roll_dice(2, 6), rng.roll_dice(1, 2) == 1)
+    };
+    for _i in 0..count {
+        if arrows { arrow(ecs, x, y); } else { crossbow_bolt(ecs, x, y); }
+    }
+}
+
+fn leather_armour(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('['),
+            fg: RGB::named(BROWN1),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Leather Armour".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 10.0 })
+        .with(Value { gold: 25 })
+        .with(Equippable { slot: EquipmentSlot::Armour })
+        .with(Wearable { armour_class: 2 })
+        .with(Resistances { responses: vec![(DamageType::Cold, DamageResponse::Resistant)] })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn ration(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('%'),
+            fg: RGB::named(GREEN),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Ration".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 5 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(ProvidesFood {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn identify_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437(')'),
+            fg: RGB::named(WHITE),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Identify Scroll".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 20 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(Identifies {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn remove_curse_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437(')'),
+            fg: RGB::named(YELLOW),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Remove Curse Scroll".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 35 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(RemovesCurse {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn recharging_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437(')'),
+            fg: RGB::named(GREEN),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Recharging Scroll".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 50 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(Recharges {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437(')'),
+            fg: RGB::named(CYAN),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Magic Mapping Scroll".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 40 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(MagicMapper {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn teleport_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437(')'),
+            fg: RGB::named(MAGENTA),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Teleport Scroll".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 30 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(TeleportsUser {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn blink_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437(')'),
+            fg: RGB::named(WHITE),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Blink Scroll".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 35 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(Ranged { range: 8 })
+        .with(Blinks {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn detect_monsters_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437(')'),
+            fg: RGB::named(RED),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Detect Monsters Scroll".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.1 })
+        .with(Value { gold: 30 })
+        .with(Consumable {})
+        .with(Stackable {})
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Telepathy, turns: 40 } })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// Wands aren't used up, but run out of charges and sit inert until recharged
+fn wand_of_magic_missile(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(CYAN),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Wand of Magic Missile".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 80 })
+        .with(Charges { current: 5, max: 5 })
+        .with(Ranged { range: 6 })
+        .with(InflictsDamage { damage: 8, damage_type: DamageType::Magic })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn wand_of_fire(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(ORANGE),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Wand of Fire".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 120 })
+        .with(Charges { current: 3, max: 3 })
+        .with(Ranged { range: 6 })
+        .with(InflictsDamage { damage: 20, damage_type: DamageType::Fire })
+        .with(AreaOfEffect { radius: 3 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn wand_of_confusion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(PINK),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Wand of Confusion".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 90 })
+        .with(Charges { current: 4, max: 4 })
+        .with(Ranged { range: 6 })
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Confusion, turns: 4 } })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn wand_of_slowness(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(BLUE),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Wand of Slowness".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 80 })
+        .with(Charges { current: 4, max: 4 })
+        .with(Ranged { range: 6 })
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Slow, turns: 6 } })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn wand_of_paralysis(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(MAGENTA),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Wand of Paralysis".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 100 })
+        .with(Charges { current: 4, max: 4 })
+        .with(Ranged { range: 6 })
+        .with(InflictsStatus { effect: StatusEffect { kind: StatusKind::Paralysis, turns: 3 } })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn wand_of_digging(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(BROWN1),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Wand of Digging".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 0.5 })
+        .with(Value { gold: 100 })
+        .with(Charges { current: 4, max: 4 })
+        .with(Ranged { range: 6 })
+        .with(Digs {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// Cursed equipment looks just like the real thing, which is the point
+fn cursed_longsword(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('/'),
+            fg: RGB::named(YELLOW),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Longsword -2 (cursed)".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 3.0 })
+        .with(Value { gold: 40 })
+        .with(Equippable { slot: EquipmentSlot::Melee })
+        .with(MeleeWeapon { hit_bonus: -2, damage: Dice { n_dice: 1, die_type: 8, bonus: -2 }, damage_type: DamageType::Physical })
+        .with(Cursed {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn cursed_leather_armour(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: to_cp437('['),
+            fg: RGB::named(BROWN1),
+            bg: RGB::named(BLACK),
+            render_order: 2,
+        })
+        .with(Name { name: "Leather Armour -1 (cursed)".to_string() })
+        .with(Item {})
+        .with(Weight { lbs: 10.0 })
+        .with(Value { gold: 25 })
+        .with(Equippable { slot: EquipmentSlot::Armour })
+        .with(Wearable { armour_class: -1 })
+        .with(Cursed {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
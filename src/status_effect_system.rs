@@ -0,0 +1,52 @@
+use specs::prelude::*;
+use crate::components::{CombatStats, DamageType, MyTurn, StatusEffects, StatusKind, SufferDamage};
+use crate::gamelog::GameLog;
+
+/// Counts down the status effects of each creature taking a turn, applying any per-turn effects
+/// and removing the ones that have run out.
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (Entities<'a>,
+                       ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       WriteStorage<'a, StatusEffects>,
+                       WriteStorage<'a, CombatStats>,
+                       WriteStorage<'a, SufferDamage>,
+                       ReadStorage<'a, MyTurn>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, player_entity, mut gamelog, mut status_effects, mut combat_stats, mut suffer_damage, turns) = data;
+
+        let mut cured = Vec::new();
+        for (entity, statuses, _turn) in (&entities, &mut status_effects, &turns).join() {
+            for effect in statuses.effects.iter_mut() {
+                match effect.kind {
+                    StatusKind::Poison => SufferDamage::new_damage(&mut suffer_damage, entity, 1, DamageType::Poison),
+                    StatusKind::Regeneration => {
+                        if let Some(stats) = combat_stats.get_mut(entity) {
+                            stats.hp = i32::min(stats.max_hp, stats.hp + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                effect.turns -= 1;
+            }
+
+            for effect in statuses.effects.iter().filter(|e| e.turns < 1) {
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You are no longer {}.", effect.kind.adjective()));
+                }
+            }
+            statuses.effects.retain(|e| e.turns > 0);
+            if statuses.effects.is_empty() {
+                cured.push(entity);
+            }
+        }
+
+        for entity in cured {
+            status_effects.remove(entity);
+        }
+    }
+}
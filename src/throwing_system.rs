@@ -0,0 +1,126 @@
+use bracket_lib::prelude::*;
+use specs::prelude::*;
+use crate::components::{CombatStats, Cursed, DamageType, Equipped, InBackpack, InflictsStatus, Name, Position, ProvidesHealing, Shatters, StatusEffects, SufferDamage, WantsToThrowItem, Weight};
+use crate::gamelog::GameLog;
+use crate::identification::ItemNames;
+use crate::map::{Map, TileType};
+
+/// How far anything can be thrown.
+pub const THROW_RANGE: i32 = 6;
+
+/// How far a shattered potion splashes from where it lands.
+const SPLASH_RADIUS: i32 = 1;
+
+/// Sends thrown items along a line towards their target. They stop short of walls, hit the first
+/// creature in their way, and come to rest where they stop, unless they shatter. Cursed gear that
+/// is being worn can no more be thrown than dropped.
+pub struct ThrowingSystem {}
+
+impl<'a> System<'a> for ThrowingSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (ReadExpect<'a, Entity>,
+                       WriteExpect<'a, GameLog>,
+                       ReadExpect<'a, Map>,
+                       WriteExpect<'a, ItemNames>,
+                       Entities<'a>,
+                       WriteStorage<'a, WantsToThrowItem>,
+                       ReadStorage<'a, Name>,
+                       WriteStorage<'a, Position>,
+                       WriteStorage<'a, InBackpack>,
+                       ReadStorage<'a, Weight>,
+                       ReadStorage<'a, Shatters>,
+                       ReadStorage<'a, ProvidesHealing>,
+                       ReadStorage<'a, InflictsStatus>,
+                       WriteStorage<'a, CombatStats>,
+                       WriteStorage<'a, StatusEffects>,
+                       WriteStorage<'a, SufferDamage>,
+                       ReadStorage<'a, Cursed>,
+                       ReadStorage<'a, Equipped>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut gamelog, map, mut item_names, entities, mut wants_throw, names, mut positions, mut backpack,
+            weights, shatters, healing, inflicts_status, mut combat_stats, mut status_effects, mut suffer_damage, cursed, equipped) = data;
+
+        for (entity, throw) in (&entities, &wants_throw).join() {
+            let is_player = entity == *player_entity;
+            if cursed.get(throw.item).is_some() && equipped.get(throw.item).is_some() {
+                let real_name = &names.get(throw.item).unwrap().name;
+                if is_player {
+                    gamelog.entries.push(format!("You can't let go of the {}; it's cursed!", item_names.describe(real_name)));
+                    item_names.identify(real_name);
+                }
+                continue;
+            }
+
+            let thrower_pos = *positions.get(entity).unwrap();
+            let start = Point::new(thrower_pos.x, thrower_pos.y);
+            let item_name = &names.get(throw.item).unwrap().name;
+
+            // Follow the line until it reaches the target, a wall or a creature
+            let mut landing = start;
+            let mut struck = None;
+            for point in line2d(LineAlg::Bresenham, start, throw.target).into_iter().filter(|point| *point != start) {
+                let idx = map.xy_idx(point.x, point.y);
+                if map.tiles[idx] == TileType::Wall { break; }
+                landing = point;
+                struck = map.tile_content[idx].iter().find(|mob| combat_stats.get(**mob).is_some()).copied();
+                if struck.is_some() { break; }
+            }
+
+            backpack.remove(throw.item);
+            if is_player {
+                gamelog.entries.push(format!("You throw the {}.", item_names.describe(item_name)));
+            }
+
+            if shatters.get(throw.item).is_some() {
+                if is_player {
+                    let obscured_name = item_names.describe(item_name);
+                    gamelog.entries.push(format!("The {} shatters!", obscured_name));
+                    if item_names.identify(item_name) {
+                        gamelog.entries.push(format!("The {} turns out to be a {}.", obscured_name, item_name));
+                    }
+                }
+
+                let mut splash_tiles = field_of_view(landing, SPLASH_RADIUS, &*map);
+                splash_tiles.retain(|p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1);
+                for tile in splash_tiles.iter() {
+                    for mob in map.tile_content[map.xy_idx(tile.x, tile.y)].iter() {
+                        let Some(stats) = combat_stats.get_mut(*mob) else { continue };
+                        let mob_name = &names.get(*mob).unwrap().name;
+                        if let Some(healer) = healing.get(throw.item) {
+                            stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
+                            if is_player {
+                                gamelog.entries.push(format!("{} is splashed, healing {} hp.", mob_name, healer.heal_amount));
+                            }
+                        }
+                        if let Some(inflicts) = inflicts_status.get(throw.item) {
+                            StatusEffects::add_effect(&mut status_effects, *mob, inflicts.effect);
+                            if is_player {
+                                gamelog.entries.push(format!("{} is splashed, and is now {}.", mob_name, inflicts.effect.kind.adjective()));
+                            }
+                        }
+                    }
+                }
+
+                entities.delete(throw.item).expect("Delete failed");
+                continue;
+            }
+
+            if let Some(victim) = struck {
+                let damage = thrown_damage(weights.get(throw.item).map_or(0.0, |weight| weight.lbs));
+                SufferDamage::new_damage(&mut suffer_damage, victim, damage, DamageType::Physical);
+                if is_player {
+                    gamelog.entries.push(format!("The {} hits {}.", item_names.describe(item_name), &names.get(victim).unwrap().name));
+                }
+            }
+            positions.insert(throw.item, Position { x: landing.x, y: landing.y }).expect("Unable to insert position");
+        }
+
+        wants_throw.clear();
+    }
+}
+
+/// Anything thrown does a point of damage, and another for every two pounds it weighs.
+fn thrown_damage(lbs: f32) -> i32 {
+    1 + (lbs / 2.0) as i32
+}